@@ -0,0 +1,50 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::convert::{TryFrom, TryInto};
+
+use lib_tests::person::{AreaCode, Designer, Job};
+use lib_tests::proxy;
+use protobuf_gen::ProtobufGen;
+use test::{black_box, Bencher};
+
+#[bench]
+fn bench_enum_from_i32(b: &mut Bencher) {
+    b.iter(|| {
+        for n in 0..4 {
+            let _ = black_box(AreaCode::try_from(black_box(n)));
+        }
+    });
+}
+
+#[bench]
+fn bench_enum_proto_names(b: &mut Bencher) {
+    b.iter(|| {
+        for name in AreaCode::PROTO_NAMES {
+            let area_code = AreaCode::from_proto_name(black_box(name)).unwrap();
+            black_box(area_code.as_proto_name());
+        }
+    });
+}
+
+#[bench]
+fn bench_enum_decode(b: &mut Bencher) {
+    let mut buffer = Vec::new();
+    AreaCode::Jinhae.to_protobuf(&mut buffer).unwrap();
+    b.iter(|| AreaCode::from_protobuf(&mut black_box(&buffer[..])).unwrap());
+}
+
+#[bench]
+fn bench_one_of_conversion(b: &mut Bencher) {
+    let job = Job::Designer { designer: Designer { id: 1, name: "Kim".to_string() } };
+    b.iter(|| {
+        let proxy: proxy::Job = black_box(job.clone()).try_into().unwrap();
+        Job::try_from(proxy).unwrap()
+    });
+}
+
+#[bench]
+fn bench_one_of_empty(b: &mut Bencher) {
+    b.iter(|| Job::try_from(black_box(proxy::Job { inner: None })).is_err());
+}
@@ -6,6 +6,11 @@ pub fn main() -> result::Result<(), ConfigError> {
     let mut config = Config::new("protos", Some("proxy"));
     config.add_source("src/person.rs", "yellow_book");
     config.add_source("src/city.rs", "yellow_book");
+    config.add_source("src/account.rs", "yellow_book");
+    config.add_source("src/directory.rs", "yellow_book");
+    config.add_source("src/geometry.rs", "yellow_book");
+    config.add_source("src/sensor.rs", "yellow_book");
+    config.descriptor_set("protos/yellow_book.bin");
 
     config.generate()?;
     Ok(())
@@ -0,0 +1,98 @@
+use protobuf_gen::ProtobufGen;
+
+use crate::city::City;
+
+#[derive(Debug, Default, Clone, ProtobufGen, Arbitrary, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", proto_default)]
+pub struct Account {
+    pub id: u32,
+    #[protobuf_gen(encrypt = "pii")]
+    pub email: String,
+}
+
+#[derive(Debug, Clone, Copy, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", inherent)]
+pub enum Tier {
+    Free,
+    Paid,
+}
+
+#[derive(Debug, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", inherent)]
+pub struct Session {
+    pub account_id: u32,
+    pub tier: Tier,
+}
+
+#[derive(Debug, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy")]
+pub struct Login {
+    pub session: Session,
+    pub previous: Vec<Session>,
+}
+
+#[derive(Debug, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy")]
+pub enum SessionEvent {
+    Started(Session),
+    Ended,
+}
+
+#[derive(Debug, Default, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", proto_default)]
+pub struct Profile {
+    pub nickname: String,
+    pub hometown: Option<City>,
+}
+
+/// The account which referred a new account, if any.
+#[derive(Debug, Clone, Copy, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", optional, proto_default)]
+pub enum Referrer {
+    None,
+    Account(u32),
+}
+
+#[derive(Debug, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", optional, proto_default)]
+pub enum Hometown {
+    Unknown,
+    Known(City),
+}
+
+#[derive(Debug, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy")]
+pub struct Signup {
+    pub account_id: u32,
+    pub referrer: Referrer,
+    pub hometown: Hometown,
+}
+
+/// Preferences of an account, which older clients send only in part.
+#[derive(Debug, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", default)]
+pub struct Preferences {
+    pub language: String,
+    pub home: City,
+    pub tier: Tier,
+    pub contact: Contact,
+}
+
+#[derive(Debug, Default, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", default)]
+pub enum Contact {
+    #[default]
+    None,
+    Email(String),
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            language: "en".to_string(),
+            home: City { name: "Seoul".to_string() },
+            tier: Tier::Free,
+            contact: Contact::None,
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! Serves `Directory` over stdin/stdout, for tests of `PluginProcess`.
+
+use lib_tests::directory::Directory;
+use protobuf_gen::plugin;
+
+fn main() -> Result<(), protobuf_gen::Error> {
+    plugin::serve_stdio(&mut Directory::default())
+}
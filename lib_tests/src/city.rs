@@ -1,7 +1,7 @@
 use protobuf_gen::ProtobufGen;
 
 #[derive(Debug, Default, Clone, ProtobufGen, Arbitrary, PartialEq)]
-#[protobuf_gen(proxy_mod = "crate::proxy")]
+#[protobuf_gen(proxy_mod = "crate::proxy", schema_version = 1, doc_hidden = false, anonymize, ffi)]
 pub struct City {
     pub name: String,
 }
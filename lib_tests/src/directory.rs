@@ -0,0 +1,66 @@
+use std::fmt;
+
+use protobuf_gen::plugin::Plugin;
+use protobuf_gen::ProtobufGen;
+
+use crate::city::City;
+
+#[derive(Debug, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", dispatch = "DirectoryResponse")]
+pub enum DirectoryRequest {
+    Register(City),
+    Count,
+}
+
+#[derive(Debug, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy")]
+pub enum DirectoryResponse {
+    Registered { total: u32 },
+    Count(u32),
+}
+
+/// A plugin answering `DirectoryRequest`s, served by the `directory_plugin` binary.
+#[derive(Default)]
+pub struct Directory(Vec<City>);
+
+impl Plugin for Directory {
+    type Request = DirectoryRequest;
+    type Response = DirectoryResponse;
+
+    fn handle(&mut self, request: DirectoryRequest) -> DirectoryResponse {
+        request.dispatch(self)
+    }
+}
+
+impl DirectoryRequestHandler for Directory {
+    fn register(&mut self, city: City) -> DirectoryResponse {
+        self.0.push(city);
+        DirectoryResponse::Registered { total: self.0.len() as u32 }
+    }
+
+    fn count(&mut self) -> DirectoryResponse {
+        DirectoryResponse::Count(self.0.len() as u32)
+    }
+}
+
+#[derive(Debug, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", status = "yellow_book.DirectoryError")]
+pub enum DirectoryError {
+    #[protobuf_gen(code = "NOT_FOUND")]
+    NotFound(City),
+    #[protobuf_gen(code = "RESOURCE_EXHAUSTED")]
+    Full {
+        capacity: u32,
+    },
+    Corrupted,
+}
+
+impl fmt::Display for DirectoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DirectoryError::NotFound(city) => write!(f, "no entry for {}", city.name),
+            DirectoryError::Full { capacity } => write!(f, "directory is full at {}", capacity),
+            DirectoryError::Corrupted => write!(f, "directory is corrupted"),
+        }
+    }
+}
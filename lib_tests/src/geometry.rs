@@ -0,0 +1,15 @@
+// Stands in for types which cannot be annotated, e.g. `bindgen` output.
+macro_rules! define_point {
+    ($name:ident) => {
+        #[derive(Debug, Default, Clone, PartialEq)]
+        pub struct $name {
+            pub x: i32,
+            pub y: i32,
+            pub label: String,
+        }
+    };
+}
+
+define_point!(Point);
+
+protobuf_gen::conversions!(Point => proxy_mod = crate::proxy, fields(x: i32, y: i32, label: String));
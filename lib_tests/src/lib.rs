@@ -1,8 +1,12 @@
 #[macro_use]
 extern crate proptest_derive;
 
+pub mod account;
 pub mod city;
+pub mod directory;
+pub mod geometry;
 pub mod person;
+pub mod sensor;
 
 pub mod proxy {
     include!("../proxy/yellow_book.rs");
@@ -1,5 +1,3 @@
-use std::convert::TryInto;
-
 use protobuf_gen::ProtobufGen;
 
 use crate::city::City;
@@ -11,14 +9,15 @@ pub struct Dummy {
 }
 
 #[derive(Debug, Default, Clone, ProtobufGen, Arbitrary, PartialEq)]
-#[protobuf_gen(proxy_mod = "crate::proxy")]
+#[protobuf_gen(proxy_mod = "crate::proxy", anonymize, unique_by = "id", proto_default)]
 pub struct Designer {
     pub id: i32,
+    #[protobuf_gen(sensitive)]
     pub name: String,
 }
 
 #[derive(Debug, Clone, ProtobufGen, Arbitrary, PartialEq)]
-#[protobuf_gen(proxy_mod = "crate::proxy")]
+#[protobuf_gen(proxy_mod = "crate::proxy", doc_hidden = false, anonymize, proto_default)]
 pub enum Job {
     None,
     Programmer { skill: String, grade: u8 },
@@ -31,8 +30,15 @@ impl Default for Job {
     }
 }
 
+/// A job being filled in, whose unset job is taken from the default one.
+#[derive(Debug, Default, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", default)]
+pub struct Draft {
+    pub job: Job,
+}
+
 #[derive(Debug, Clone, ProtobufGen, Arbitrary, PartialEq)]
-#[protobuf_gen(proxy_mod = "crate::proxy")]
+#[protobuf_gen(proxy_mod = "crate::proxy", doc_hidden = false, anonymize, proto_default)]
 pub enum AreaCode {
     Seoul,
     Seongnam,
@@ -55,7 +61,7 @@ impl From<Vec<u8>> for NumberBuffer {
 }
 
 #[derive(Debug, Clone, ProtobufGen, Arbitrary, PartialEq)]
-#[protobuf_gen(proxy_mod = "crate::proxy")]
+#[protobuf_gen(proxy_mod = "crate::proxy", anonymize)]
 pub struct Person {
     pub(crate) _inner: i32,
     pub id: u8,
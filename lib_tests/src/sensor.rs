@@ -0,0 +1,11 @@
+use protobuf_gen::ProtobufGen;
+
+#[derive(Debug, Default, Clone, ProtobufGen, PartialEq)]
+#[protobuf_gen(proxy_mod = "crate::proxy", wire_struct, proto_default)]
+pub struct Reading {
+    pub sensor: u16,
+    pub celsius: f32,
+    pub valid: bool,
+    pub sequence: i64,
+    pub(crate) _received_at: u64,
+}
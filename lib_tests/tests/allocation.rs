@@ -0,0 +1,59 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::convert::{TryFrom, TryInto};
+
+use lib_tests::person::{AreaCode, Job};
+use lib_tests::proxy;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+// Only the errors own the names of the types and fields.
+#[test]
+fn enum_conversions_do_not_allocate() {
+    assert_eq!(
+        allocations(|| {
+            for n in 0..3 {
+                assert!(AreaCode::try_from(n).is_ok());
+            }
+            assert_eq!(i32::from(AreaCode::Jinhae), 2);
+            for name in AreaCode::PROTO_NAMES {
+                assert!(AreaCode::from_proto_name(name).is_some());
+            }
+            assert_eq!(AreaCode::Seoul.as_proto_name(), "Seoul");
+            let _: proxy::AreaCode = AreaCode::Seongnam.try_into().unwrap();
+        }),
+        0
+    );
+
+    assert_eq!(
+        allocations(|| {
+            let job: proxy::Job = Job::None.try_into().unwrap();
+            assert!(Job::try_from(job).is_ok());
+        }),
+        0
+    );
+}
@@ -0,0 +1,106 @@
+use lib_tests::person::{Designer, Job};
+use protobuf_gen::anonymize::{self, Anonymize, Anonymizer};
+use protobuf_gen::descriptor::Descriptors;
+use protobuf_gen::ProtobufGen;
+
+fn encode(id: i32, name: &str) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    Designer { id, name: name.to_string() }.to_protobuf(&mut buffer).unwrap();
+    buffer
+}
+
+#[test]
+fn anonymize_samples() {
+    let encoded = [encode(1, "Kim Dahyun"), encode(1, "Kim Dahyun"), encode(2, "Lee")];
+
+    let samples = anonymize::sample::<Designer, _>(
+        encoded.iter().map(Vec::as_slice),
+        &mut Anonymizer::new(42),
+    )
+    .unwrap();
+    assert_eq!(samples.len(), 2);
+
+    let sample = Designer::from_protobuf(&mut &samples[0][..]).unwrap();
+    assert_eq!(sample.id, 1);
+    assert_ne!(sample.name, "Kim Dahyun");
+    assert_eq!(sample.name.len(), "Kim Dahyun".len());
+    assert_eq!(sample.name.find(' '), Some(3));
+    assert!(sample.name.starts_with(|c: char| c.is_ascii_uppercase()));
+
+    let again = anonymize::sample::<Designer, _>(
+        encoded.iter().map(Vec::as_slice),
+        &mut Anonymizer::new(42),
+    )
+    .unwrap();
+    assert_eq!(samples, again);
+}
+
+#[test]
+fn anonymize_recursively() {
+    let designer = Designer { id: 3, name: "Park".to_string() };
+    let mut job = Job::Designer { designer: designer.clone() };
+    job.anonymize(&mut Anonymizer::new(7));
+    match job {
+        Job::Designer { designer: anonymized } => {
+            assert_eq!(anonymized.id, designer.id);
+            assert_ne!(anonymized.name, designer.name);
+        }
+        job => panic!("unexpected job: {:?}", job),
+    }
+
+    let mut job = Job::Programmer { skill: "rust".to_string(), grade: 3 };
+    job.anonymize(&mut Anonymizer::new(7));
+    assert_eq!(job, Job::Programmer { skill: "rust".to_string(), grade: 3 });
+}
+
+#[test]
+fn anonymize_non_ascii() {
+    let anonymized: Vec<_> = Anonymizer::new(42).randomize_str("김철수 (Kim)").chars().collect();
+    assert_eq!(anonymized.len(), 9);
+    assert!(anonymized[..3].iter().all(|c| ('\u{ac00}'..='\u{d7a3}').contains(c)));
+    assert_ne!(anonymized[..3], ['김', '철', '수']);
+    assert_eq!(anonymized[3..5], [' ', '(']);
+    assert!(anonymized[5].is_ascii_uppercase());
+    assert!(anonymized[6..8].iter().all(char::is_ascii_lowercase));
+    assert_eq!(anonymized[8], ')');
+}
+
+#[test]
+fn anonymize_encoded_samples() {
+    let descriptors = Descriptors::load("protos/yellow_book.bin").unwrap();
+    assert!(descriptors.is_sensitive("yellow_book.Designer", "name"));
+    assert_eq!(descriptors.unique_by("yellow_book.Designer"), Some("id"));
+
+    let encoded = [encode(1, "김다현"), encode(1, "김다현"), encode(2, "Lee")];
+    let samples = anonymize::sample_encoded(
+        &descriptors,
+        "yellow_book.Designer",
+        encoded.iter().map(Vec::as_slice),
+        &mut Anonymizer::new(42),
+    )
+    .unwrap();
+    assert_eq!(samples.len(), 2);
+
+    let sample = Designer::from_protobuf(&mut &samples[0][..]).unwrap();
+    assert_eq!(sample.id, 1);
+    assert_eq!(sample.name.chars().count(), 3);
+    assert_ne!(sample.name, "김다현");
+
+    let mut encoded = Vec::new();
+    let designer = Designer { id: 3, name: "Park".to_string() };
+    Job::Designer { designer: designer.clone() }.to_protobuf(&mut encoded).unwrap();
+    let samples = anonymize::sample_encoded(
+        &descriptors,
+        "yellow_book.Job",
+        vec![&encoded[..]],
+        &mut Anonymizer::new(7),
+    )
+    .unwrap();
+    match Job::from_protobuf(&mut &samples[0][..]).unwrap() {
+        Job::Designer { designer: anonymized } => {
+            assert_eq!(anonymized.id, designer.id);
+            assert_ne!(anonymized.name, designer.name);
+        }
+        job => panic!("unexpected job: {:?}", job),
+    }
+}
@@ -0,0 +1,13 @@
+use lib_tests::person::Person;
+use proptest::prelude::*;
+use protobuf_gen::conformance::Conformance;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn person_conforms_to_schema(person: Person) {
+        let conformance = Conformance::new("protos");
+        conformance.check(person, "yellow_book.proto", "yellow_book.Person").unwrap();
+    }
+}
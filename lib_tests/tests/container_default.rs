@@ -0,0 +1,57 @@
+use std::convert::TryFrom;
+
+use lib_tests::account::{Contact, Preferences, Tier};
+use lib_tests::city::City;
+use lib_tests::person::{Draft, Job};
+use lib_tests::proxy;
+
+#[test]
+fn unset_message_fields_fall_back_to_default() {
+    let preferences = Preferences::try_from(proxy::Preferences {
+        language: "ko".to_string(),
+        home: None,
+        tier: proxy::Tier::Paid as i32,
+        contact: None,
+    })
+    .unwrap();
+    assert_eq!(
+        preferences,
+        Preferences {
+            language: "ko".to_string(),
+            home: Preferences::default().home,
+            tier: Tier::Paid,
+            contact: Contact::None,
+        }
+    );
+
+    // scalars have no presence, so they are decoded as sent.
+    let preferences = Preferences::try_from(proxy::Preferences {
+        language: String::new(),
+        home: Some(proxy::City { name: "Busan".to_string() }),
+        tier: proxy::Tier::Free as i32,
+        contact: Some(proxy::Contact { inner: None }),
+    })
+    .unwrap();
+    assert_eq!(preferences.language, "");
+    assert_eq!(preferences.home, City { name: "Busan".to_string() });
+    assert_eq!(preferences.contact, Contact::None);
+}
+
+#[test]
+fn invalid_fields_still_fail() {
+    let result = Preferences::try_from(proxy::Preferences {
+        language: "ko".to_string(),
+        home: None,
+        tier: 7,
+        contact: None,
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn set_fields_with_unset_nested_fields_still_fail() {
+    assert_eq!(Draft::try_from(proxy::Draft { job: None }).unwrap(), Draft { job: Job::None });
+
+    let result = Draft::try_from(proxy::Draft { job: Some(proxy::Job { inner: None }) });
+    assert!(result.is_err());
+}
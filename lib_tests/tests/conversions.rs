@@ -0,0 +1,18 @@
+use std::convert::{TryFrom, TryInto};
+
+use lib_tests::geometry::Point;
+use lib_tests::proxy;
+use protobuf_gen::ProtobufGen;
+
+#[test]
+fn conversions_macro() {
+    let point = Point { x: -3, y: 4, label: "origin".to_string() };
+
+    let mut bytes = Vec::new();
+    point.clone().to_protobuf(&mut bytes).unwrap();
+    assert_eq!(Point::from_protobuf(&mut &bytes[..]).unwrap(), point);
+
+    let proxy: proxy::Point = point.clone().try_into().unwrap();
+    assert_eq!((proxy.x, proxy.y, proxy.label.as_str()), (-3, 4, "origin"));
+    assert_eq!(Point::try_from(proxy).unwrap(), point);
+}
@@ -0,0 +1,21 @@
+use lib_tests::proxy;
+use prost::Message;
+use protobuf_gen::corpus::Corpus;
+use protobuf_gen::descriptor::Descriptors;
+
+#[test]
+fn corpus_decodes_as_proxy() {
+    let descriptors = Descriptors::load("protos/yellow_book.bin").unwrap();
+
+    let mut corpus = Corpus::new(&descriptors, 7);
+    let messages: Vec<_> = (0..32).map(|_| corpus.message("yellow_book.Person").unwrap()).collect();
+    for message in &messages {
+        let person = proxy::Person::decode(&message[..]).unwrap();
+        assert!(person.job.and_then(|job| job.inner).is_some());
+        assert!(proxy::AreaCode::from_i32(person.area_code).is_some());
+    }
+
+    let mut again = Corpus::new(&descriptors, 7);
+    assert_eq!(again.message("yellow_book.Person").unwrap(), messages[0]);
+    assert!(again.message("yellow_book.Unknown").is_err());
+}
@@ -0,0 +1,18 @@
+use std::convert::TryFrom;
+
+use lib_tests::person::AreaCode;
+use protobuf_gen::Error;
+
+#[test]
+fn enumerator_numbers_and_names() {
+    assert_eq!(i32::from(AreaCode::Seongnam), 1);
+    assert_eq!(AreaCode::try_from(2).unwrap(), AreaCode::Jinhae);
+    assert!(matches!(AreaCode::try_from(3), Err(Error::InvalidIdent(_))));
+
+    for area_code in [AreaCode::Seoul, AreaCode::Seongnam, AreaCode::Jinhae] {
+        let name = area_code.as_proto_name();
+        assert_eq!(AreaCode::from_proto_name(name), Some(area_code));
+    }
+    assert_eq!(AreaCode::Jinhae.as_proto_name(), "Jinhae");
+    assert_eq!(AreaCode::from_proto_name("Busan"), None);
+}
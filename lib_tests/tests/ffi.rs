@@ -0,0 +1,53 @@
+use std::io::{Read, Write};
+use std::ptr;
+
+use lib_tests::city::{
+    yellow_book_city_decode, yellow_book_city_encode, yellow_book_city_free, City,
+};
+use protobuf_gen::ffi::{self, protobuf_gen_buffer_free, Buffer, Status};
+use protobuf_gen::{Error, ProtobufGen};
+
+#[test]
+fn ffi_roundtrip() {
+    let city = City { name: "Seongnam".to_string() };
+    let mut encoded = Vec::new();
+    city.clone().to_protobuf(&mut encoded).unwrap();
+
+    unsafe {
+        let decoded = yellow_book_city_decode(encoded.as_ptr(), encoded.len());
+        assert!(!decoded.is_null());
+        assert_eq!(*decoded, city);
+
+        let mut buffer = Buffer::empty();
+        assert_eq!(yellow_book_city_encode(decoded, &mut buffer), Status::Ok);
+        assert_eq!(std::slice::from_raw_parts(buffer.data, buffer.len), &encoded[..]);
+        protobuf_gen_buffer_free(buffer);
+        yellow_book_city_free(decoded);
+
+        assert!(yellow_book_city_decode([0xff].as_ptr(), 1).is_null());
+        assert_eq!(yellow_book_city_encode(ptr::null(), &mut Buffer::empty()), Status::NullPointer);
+    }
+}
+
+#[derive(Clone)]
+struct Panicking;
+
+impl ProtobufGen for Panicking {
+    type Error = Error;
+
+    fn to_protobuf<W: Write>(self, _: &mut W) -> Result<(), Error> {
+        panic!("to_protobuf")
+    }
+
+    fn from_protobuf<R: Read>(_: &mut R) -> Result<Self, Error> {
+        panic!("from_protobuf")
+    }
+}
+
+#[test]
+fn ffi_catches_panics() {
+    unsafe {
+        assert!(ffi::decode::<Panicking>([0].as_ptr(), 1).is_null());
+        assert_eq!(ffi::encode(&Panicking, &mut Buffer::empty()), Status::Panicked);
+    }
+}
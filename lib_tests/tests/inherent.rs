@@ -0,0 +1,39 @@
+use lib_tests::account::{Login, Session, SessionEvent, Tier};
+use lib_tests::proxy;
+use protobuf_gen::ProtobufGen;
+
+#[test]
+fn inherent_conversions() {
+    let session = Session { account_id: 3, tier: Tier::Paid };
+
+    let proxy = session.clone().to_proxy().unwrap();
+    assert_eq!(proxy, proxy::Session { account_id: 3, tier: proxy::Tier::Paid as i32 });
+    assert_eq!(Session::from_proxy(proxy).unwrap(), session);
+
+    let mut buffer = Vec::new();
+    session.clone().to_protobuf(&mut buffer).unwrap();
+    assert_eq!(Session::from_protobuf(&mut &buffer[..]).unwrap(), session);
+
+    assert_eq!(Tier::Free.to_proxy().unwrap(), proxy::Tier::Free);
+    assert_eq!(Tier::from_proxy(proxy::Tier::Free).unwrap(), Tier::Free);
+}
+
+#[test]
+fn inherent_fields() {
+    let session = Session { account_id: 3, tier: Tier::Paid };
+    let login = Login { session: session.clone(), previous: vec![session.clone()] };
+
+    let mut buffer = Vec::new();
+    login.clone().to_protobuf(&mut buffer).unwrap();
+    assert_eq!(Login::from_protobuf(&mut &buffer[..]).unwrap(), login);
+
+    let event = SessionEvent::Started(session);
+    let mut buffer = Vec::new();
+    event.clone().to_protobuf(&mut buffer).unwrap();
+    assert_eq!(SessionEvent::from_protobuf(&mut &buffer[..]).unwrap(), event);
+
+    let proxy = proxy::Login { session: None, previous: Vec::new() };
+    let mut buffer = Vec::new();
+    prost::Message::encode(&proxy, &mut buffer).unwrap();
+    assert!(Login::from_protobuf(&mut &buffer[..]).is_err());
+}
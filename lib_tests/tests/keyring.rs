@@ -0,0 +1,46 @@
+use lib_tests::account::Account;
+use lib_tests::proxy;
+use prost::Message;
+use protobuf_gen::keyring::{self, KeyringError};
+use protobuf_gen::{Keyring, ProtobufGen};
+
+struct XorKeyring;
+
+impl Keyring for XorKeyring {
+    fn encrypt(&self, key: &str, plaintext: &[u8]) -> Result<Vec<u8>, KeyringError> {
+        assert_eq!(key, "pii");
+        Ok(plaintext.iter().map(|b| b ^ 0x5a).collect())
+    }
+
+    fn decrypt(&self, key: &str, ciphertext: &[u8]) -> Result<Vec<u8>, KeyringError> {
+        self.encrypt(key, ciphertext)
+    }
+}
+
+#[test]
+fn encrypted_field_roundtrip() {
+    let account = Account { id: 7, email: "someone@example.com".to_string() };
+
+    let mut buffer = Vec::new();
+    assert!(account.clone().to_protobuf(&mut buffer).is_err());
+
+    keyring::set_keyring(XorKeyring);
+    account.clone().to_protobuf(&mut buffer).unwrap();
+
+    let encoded = proxy::Account::decode(&buffer[..]).unwrap();
+    assert_eq!(encoded.id, 7);
+    assert_ne!(encoded.email, account.email.as_bytes());
+
+    assert_eq!(Account::from_protobuf(&mut &buffer[..]).unwrap(), account);
+    keyring::clear_keyring();
+}
+
+#[test]
+fn unset_encrypted_field_decodes_to_default() {
+    let mut buffer = Vec::new();
+    proxy::Account { id: 7, email: Vec::new() }.encode(&mut buffer).unwrap();
+
+    // without a keyring, since there is nothing to decrypt
+    let account = Account::from_protobuf(&mut &buffer[..]).unwrap();
+    assert_eq!(account, Account { id: 7, email: String::new() });
+}
@@ -0,0 +1,29 @@
+use std::fs;
+
+use protobuf_gen::descriptor::Descriptors;
+use protobuf_gen::metrics::SizeMetrics;
+
+#[test]
+fn field_sizes_of_job_fixture() {
+    let descriptors = Descriptors::load("protos/yellow_book.bin").unwrap();
+    let mut metrics = SizeMetrics::new(&descriptors, "yellow_book.Job").unwrap();
+    metrics.add_corpus(&fs::read("tests/fixtures/job-0.4.0.bin").unwrap()).unwrap();
+
+    assert_eq!(metrics.messages(), 3);
+    assert_eq!(metrics.bytes(), 23);
+
+    let bytes = |path| metrics.field(path).map(|size| size.bytes);
+    assert_eq!(bytes("Job.None"), Some(2));
+    assert_eq!(bytes("Job.Programmer"), Some(10));
+    assert_eq!(bytes("Job.Programmer.skill"), Some(6));
+    assert_eq!(bytes("Job.Programmer.grade"), Some(2));
+    assert_eq!(bytes("Job.Designer.designer.name"), Some(5));
+
+    let programmer = metrics.field("Job.Programmer").unwrap();
+    assert_eq!((programmer.messages, programmer.max), (1, 10));
+    assert_eq!(programmer.histogram(), [(8..16, 1)]);
+    assert_eq!(metrics.fields()[0].0, "Job.Designer");
+
+    assert!(metrics.add(&[0x0a]).is_err());
+    assert!(SizeMetrics::new(&descriptors, "yellow_book.Unknown").is_err());
+}
@@ -0,0 +1,53 @@
+use std::sync::{Arc, Mutex};
+
+use lib_tests::city::City;
+use protobuf_gen::{observer, ConversionObserver, Direction, ProtobufGen};
+
+type Event = (&'static str, Direction, usize, bool);
+
+struct Recorder(Arc<Mutex<Vec<Event>>>);
+
+impl ConversionObserver for Recorder {
+    fn observe(&self, message: &'static str, direction: Direction, size: usize, success: bool) {
+        self.0.lock().unwrap().push((message, direction, size, success));
+    }
+}
+
+// Clears itself on the first conversion, which would deadlock if it were called under the lock.
+struct Once(Arc<Mutex<Vec<Event>>>);
+
+impl ConversionObserver for Once {
+    fn observe(&self, message: &'static str, direction: Direction, size: usize, success: bool) {
+        self.0.lock().unwrap().push((message, direction, size, success));
+        observer::clear_observer();
+    }
+}
+
+#[test]
+fn observer_receives_conversions() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    observer::set_observer(Recorder(events.clone()));
+
+    let mut buffer = Vec::new();
+    City { name: "Seoul".to_string() }.to_protobuf(&mut buffer).unwrap();
+    City::from_protobuf(&mut &buffer[..]).unwrap();
+    assert!(City::from_protobuf(&mut &[0xff][..]).is_err());
+    observer::clear_observer();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            ("City", Direction::Encode, buffer.len(), true),
+            ("City", Direction::Decode, buffer.len(), true),
+            ("City", Direction::Decode, 1, false),
+        ]
+    );
+
+    // in the same test, since the observer is global
+    let events = Arc::new(Mutex::new(Vec::new()));
+    observer::set_observer(Once(events.clone()));
+    City { name: "Seoul".to_string() }.to_protobuf(&mut Vec::new()).unwrap();
+    City::from_protobuf(&mut &buffer[..]).unwrap();
+
+    assert_eq!(*events.lock().unwrap(), vec![("City", Direction::Encode, buffer.len(), true)]);
+}
@@ -0,0 +1,21 @@
+use std::convert::{TryFrom, TryInto};
+
+use lib_tests::city::City;
+use lib_tests::directory::{DirectoryRequest, DirectoryResponse};
+use lib_tests::proxy::{self, directory_request, directory_response};
+
+#[test]
+fn one_of_variant_with_unnamed_field() {
+    let request = DirectoryRequest::Register(City { name: "Seoul".to_string() });
+    let proxy: proxy::DirectoryRequest = request.clone().try_into().unwrap();
+    match &proxy.inner {
+        Some(directory_request::Inner::Register(city)) => assert_eq!(city.name, "Seoul"),
+        inner => panic!("{:?}", inner),
+    }
+    assert_eq!(DirectoryRequest::try_from(proxy).unwrap(), request);
+
+    let response = DirectoryResponse::Count(3);
+    let proxy: proxy::DirectoryResponse = response.clone().try_into().unwrap();
+    assert_eq!(proxy.inner, Some(directory_response::Inner::Count(3)));
+    assert_eq!(DirectoryResponse::try_from(proxy).unwrap(), response);
+}
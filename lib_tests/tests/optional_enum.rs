@@ -0,0 +1,47 @@
+use std::convert::TryInto;
+
+use lib_tests::account::{Hometown, Referrer, Signup};
+use lib_tests::city::City;
+use lib_tests::proxy;
+use protobuf_gen::{ProtoDefault, ProtobufGen};
+
+#[test]
+fn optional_enums_roundtrip() {
+    let signups = [
+        Signup { account_id: 1, referrer: Referrer::None, hometown: Hometown::Unknown },
+        Signup {
+            account_id: 2,
+            referrer: Referrer::Account(0),
+            hometown: Hometown::Known(City::default()),
+        },
+    ];
+    for signup in &signups {
+        let mut bytes = Vec::new();
+        signup.clone().to_protobuf(&mut bytes).unwrap();
+        assert_eq!(&Signup::from_protobuf(&mut &bytes[..]).unwrap(), signup);
+    }
+
+    let proxy: proxy::Signup = signups[0].clone().try_into().unwrap();
+    assert_eq!(proxy.referrer, None);
+    assert_eq!(proxy.hometown, None);
+
+    let proxy: proxy::Signup = signups[1].clone().try_into().unwrap();
+    assert_eq!(proxy.referrer, Some(0));
+    assert!(proxy.hometown.is_some());
+}
+
+#[test]
+fn optional_enums_are_plain_fields() {
+    let schema = std::fs::read_to_string("protos/yellow_book.proto").unwrap();
+    assert!(schema.contains("optional uint32 referrer = 2;"));
+    assert!(schema.contains("optional City hometown = 3;"));
+    assert!(!schema.contains("message Referrer"));
+    assert!(!schema.contains("message Hometown"));
+}
+
+#[test]
+fn optional_enums_default_to_none() {
+    assert!(Referrer::None.is_proto_default());
+    assert!(!Referrer::Account(0).is_proto_default());
+    assert!(!Hometown::Known(City::default()).is_proto_default());
+}
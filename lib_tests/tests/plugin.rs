@@ -0,0 +1,51 @@
+use std::io::Cursor;
+use std::process::Command;
+
+use lib_tests::city::City;
+use lib_tests::directory::{Directory, DirectoryRequest, DirectoryResponse};
+use protobuf_gen::plugin::{self, PluginProcess};
+
+#[test]
+fn plugin_serves_framed_requests() {
+    let mut requests = Vec::new();
+    for request in [
+        DirectoryRequest::Register(City { name: "Seoul".to_string() }),
+        DirectoryRequest::Register(City { name: "Busan".to_string() }),
+        DirectoryRequest::Count,
+    ] {
+        plugin::write_message(&mut requests, request).unwrap();
+    }
+
+    let mut responses = Vec::new();
+    plugin::serve(&mut Directory::default(), &mut Cursor::new(requests), &mut responses).unwrap();
+
+    let mut responses = Cursor::new(responses);
+    let mut read = || plugin::read_message::<_, DirectoryResponse>(&mut responses).unwrap();
+    assert_eq!(read(), Some(DirectoryResponse::Registered { total: 1 }));
+    assert_eq!(read(), Some(DirectoryResponse::Registered { total: 2 }));
+    assert_eq!(read(), Some(DirectoryResponse::Count(2)));
+    assert_eq!(read(), None);
+}
+
+#[test]
+fn plugin_process_calls_subprocess() {
+    let mut process = PluginProcess::<DirectoryRequest, DirectoryResponse>::spawn(
+        &mut Command::new(env!("CARGO_BIN_EXE_directory_plugin")),
+    )
+    .unwrap();
+
+    let request = DirectoryRequest::Register(City { name: "Seoul".to_string() });
+    assert_eq!(process.call(request).unwrap(), DirectoryResponse::Registered { total: 1 });
+    assert_eq!(process.call(DirectoryRequest::Count).unwrap(), DirectoryResponse::Count(1));
+    assert!(process.finish().unwrap().success());
+}
+
+#[test]
+fn read_message_rejects_truncated_message() {
+    // claims a message of 2^32 bytes, which must not be allocated up front
+    let mut framed = Vec::new();
+    prost::encoding::encode_varint(1 << 32, &mut framed);
+    framed.push(0);
+
+    assert!(plugin::read_message::<_, DirectoryResponse>(&mut Cursor::new(framed)).is_err());
+}
@@ -0,0 +1,25 @@
+use lib_tests::account::{Account, Profile};
+use lib_tests::city::City;
+use lib_tests::person::{AreaCode, Designer, Job};
+use lib_tests::sensor::Reading;
+use protobuf_gen::ProtoDefault;
+
+#[test]
+fn proto_default_of_messages_and_enums() {
+    assert!(Designer::default().is_proto_default());
+    assert!(!Designer { id: 0, name: "Kim".to_string() }.is_proto_default());
+
+    assert!(AreaCode::Seoul.is_proto_default());
+    assert!(!AreaCode::Jinhae.is_proto_default());
+    assert!(!Job::None.is_proto_default());
+
+    let mut reading = Reading::default();
+    assert!(reading.is_proto_default());
+    reading.valid = true;
+    assert!(!reading.is_proto_default());
+
+    assert!(Profile::default().is_proto_default());
+    assert!(!Profile { hometown: Some(City::default()), ..Profile::default() }.is_proto_default());
+
+    assert!(!Account::default().is_proto_default());
+}
@@ -0,0 +1,75 @@
+use std::fs::{self, File};
+
+use lib_tests::person::{Designer, Job};
+use prost::encoding::WireType;
+use protobuf_gen::descriptor::Descriptors;
+use protobuf_gen::replay::{self, Incompatibility, IncompatibilityKind, Replay};
+
+// Corpora recorded by earlier releases. A release adds its own with `record_job_fixture`.
+//
+// `job-0.4.0.bin` was recorded at 4ff2224, the 0.4.0 release, which predates `replay::record`:
+// `jobs()` encoded there with `to_protobuf`, each prefixed with its length as a varint.
+const FIXTURES: &[&str] = &["tests/fixtures/job-0.4.0.bin"];
+
+fn jobs() -> Vec<Job> {
+    vec![
+        Job::None,
+        Job::Programmer { skill: "rust".to_string(), grade: 3 },
+        Job::Designer { designer: Designer { id: -7, name: "Kim".to_string() } },
+    ]
+}
+
+#[test]
+#[ignore]
+fn record_job_fixture() {
+    let mut w = File::create(format!("tests/fixtures/job-{}.bin", replay::RELEASE)).unwrap();
+    replay::record(jobs(), &mut w).unwrap();
+}
+
+#[test]
+fn replay_fixtures() {
+    let descriptors = Descriptors::load("protos/yellow_book.bin").unwrap();
+    for fixture in FIXTURES {
+        let corpus = fs::read(fixture).unwrap();
+        let report = Replay::new(&descriptors).run::<Job>("yellow_book.Job", &corpus).unwrap();
+        assert!(report.is_compatible(), "{}: {}", fixture, report);
+        assert_eq!(report.frames, jobs().len());
+    }
+}
+
+#[test]
+fn replay_reports_incompatibilities() {
+    let descriptors = Descriptors::load("protos/yellow_book.bin").unwrap();
+    let corpus = [
+        // `grade` encoded as a string, and a variant numbered 4 that is not defined anymore.
+        &[0x0d, 0x12, 0x09, 0x0a, 0x04, b'r', b'u', b's', b't', 0x12, 0x01, b'A', 0x20, 0x01][..],
+        // `grade` out of the range of `u8`.
+        &[0x05, 0x12, 0x03, 0x10, 0xac, 0x02],
+    ]
+    .concat();
+
+    let report = Replay::new(&descriptors).run::<Job>("yellow_book.Job", &corpus).unwrap();
+    assert_eq!(report.frames, 2);
+
+    let found: Vec<_> =
+        report.incompatibilities.iter().map(|i| (i.frame, i.path.as_str())).collect();
+    assert_eq!(found, [(0, "Job.Programmer.grade"), (0, "Job.#4"), (0, "Job"), (1, "Job")]);
+    assert_eq!(
+        report.incompatibilities[0],
+        Incompatibility {
+            frame: 0,
+            path: "Job.Programmer.grade".to_string(),
+            kind: IncompatibilityKind::WireTypeMismatch {
+                expected: WireType::Varint,
+                found: WireType::LengthDelimited,
+            },
+        }
+    );
+    assert_eq!(
+        report.incompatibilities[1].kind,
+        IncompatibilityKind::UnknownField(WireType::Varint)
+    );
+    assert!(matches!(report.incompatibilities[3].kind, IncompatibilityKind::Rejected(_)));
+
+    assert!(Replay::new(&descriptors).run::<Job>("yellow_book.Job", &corpus[..4]).is_err());
+}
@@ -0,0 +1,41 @@
+use std::convert::TryFrom;
+
+use lib_tests::city::City;
+use lib_tests::directory::DirectoryError;
+use protobuf_gen::status::{Code, Status, STATUS_PROTO_PATH, TYPE_URL_PREFIX};
+
+#[test]
+fn errors_convert_to_status_and_back() {
+    let errors = [
+        DirectoryError::NotFound(City { name: "Jinhae".to_string() }),
+        DirectoryError::Full { capacity: 3 },
+        DirectoryError::Corrupted,
+    ];
+    let codes = [Code::NotFound, Code::ResourceExhausted, Code::Unknown];
+    for (error, &code) in errors.iter().zip(&codes) {
+        let status = Status::from(error.clone());
+        assert_eq!(status.canonical_code(), Some(code));
+        assert_eq!(status.message, error.to_string());
+        assert_eq!(status.details.len(), 1);
+        assert_eq!(
+            status.details[0].type_url,
+            format!("{}yellow_book.DirectoryError", TYPE_URL_PREFIX)
+        );
+        assert_eq!(&DirectoryError::try_from(status).unwrap(), error);
+    }
+
+    let status = Status::from(DirectoryError::Full { capacity: 3 });
+    assert_eq!(status.to_string(), "RESOURCE_EXHAUSTED: directory is full at 3");
+}
+
+#[test]
+fn statuses_without_details_do_not_convert() {
+    assert!(DirectoryError::try_from(Status::new(Code::NotFound, "no entry")).is_err());
+}
+
+#[test]
+fn status_proto_is_emitted() {
+    let schema = std::fs::read_to_string(format!("protos/{}", STATUS_PROTO_PATH)).unwrap();
+    assert!(schema.contains("package google.rpc;"));
+    assert!(schema.contains("repeated google.protobuf.Any details = 3;"));
+}
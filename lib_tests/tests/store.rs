@@ -0,0 +1,30 @@
+use lib_tests::city::City;
+use protobuf_gen::store::{ContentHash, ContentStore};
+use protobuf_gen::Error;
+
+#[test]
+fn content_store_dedups_and_verifies() {
+    let seoul = City { name: "Seoul".to_string() };
+    let busan = City { name: "Busan".to_string() };
+
+    let mut store = ContentStore::new();
+    let hashes = store.put_batch(vec![seoul.clone(), busan.clone(), seoul.clone()]).unwrap();
+    assert_eq!(store.len(), 2);
+    assert_eq!(hashes[0], hashes[2]);
+    assert_eq!(store.get_batch(&hashes[..2]).unwrap(), vec![Some(seoul), Some(busan)]);
+
+    let mut snapshot = ContentStore::<City>::new();
+    let (hash, encoded) = store.iter_encoded().next().unwrap();
+    snapshot.load(*hash, encoded.to_vec()).unwrap();
+    assert!(matches!(
+        snapshot.load(ContentHash([0; 32]), encoded.to_vec()),
+        Err(Error::IntegrityError(_))
+    ));
+    assert_eq!(snapshot.len(), 1);
+
+    // a string field of invalid UTF-8
+    let malformed = vec![0x0a, 0x01, 0xff];
+    assert!(snapshot.put_encoded(malformed.clone()).is_err());
+    assert!(snapshot.load(ContentHash::of(&malformed), malformed).is_err());
+    assert_eq!(snapshot.len(), 1);
+}
@@ -0,0 +1,29 @@
+use lib_tests::sensor::Reading;
+use protobuf_gen::{wire_struct, Error};
+
+#[test]
+fn wire_struct_layout() {
+    let mut reading = Reading::default();
+    reading.sensor = 0x0102;
+    reading.celsius = 1.5;
+    reading.valid = true;
+    reading.sequence = -2;
+
+    let bytes = reading.to_wire_struct();
+    assert_eq!(Reading::WIRE_STRUCT_LEN, 16);
+    let expected = [
+        &[wire_struct::VERSION][..],
+        &0x0102u16.to_be_bytes(),
+        &1.5f32.to_be_bytes(),
+        &[1],
+        &(-2i64).to_be_bytes(),
+    ]
+    .concat();
+    assert_eq!(bytes, expected);
+    assert_eq!(Reading::from_wire_struct(&bytes).unwrap(), reading);
+
+    assert!(matches!(Reading::from_wire_struct(&bytes[..15]), Err(Error::WireStructError(..))));
+    let mut unknown = bytes;
+    unknown[0] = wire_struct::VERSION + 1;
+    assert!(matches!(Reading::from_wire_struct(&unknown), Err(Error::WireStructError(..))));
+}
@@ -0,0 +1,120 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Attribute, Field, Fields, Item, ItemEnum, ItemStruct, Visibility};
+
+use crate::extract::diagnostic::Diagnostic;
+
+pub(crate) fn generate_anonymize(item: &Item) -> syn::Result<TokenStream> {
+    match item {
+        Item::Struct(item_struct) => generate_struct(item_struct),
+        Item::Enum(item_enum) => generate_enum(item_enum),
+        _ => unreachable!(),
+    }
+}
+
+fn generate_struct(item_struct: &ItemStruct) -> syn::Result<TokenStream> {
+    let ident = &item_struct.ident;
+    let fields: Vec<_> = item_struct
+        .fields
+        .iter()
+        .filter(|field| {
+            matches!(field.vis, Visibility::Public(_))
+                || syn_util::contains_attribute(&field.attrs, &["protobuf_gen", "expose"])
+        })
+        .collect();
+
+    let statements = fields.iter().map(|field| {
+        let field_ident = field.ident.as_ref().unwrap();
+        anonymize_field(field, quote!(&mut self.#field_ident))
+    });
+
+    let unique_key = match unique_by(&item_struct.attrs) {
+        Some(lit_str) => {
+            let key = fields
+                .iter()
+                .filter_map(|field| field.ident.as_ref())
+                .find(|field_ident| *field_ident == &lit_str.value())
+                .ok_or_else(|| {
+                    Diagnostic::InvalidAttribute.error(
+                        lit_str.span(),
+                        format!("unique_by names no public field: \"{}\"", lit_str.value()),
+                    )
+                })?;
+            quote! {
+                fn unique_key(&self) -> Option<String> {
+                    Some(protobuf_gen::anonymize::unique_key(&self.#key))
+                }
+            }
+        }
+        None => TokenStream::default(),
+    };
+
+    Ok(quote! {
+        impl protobuf_gen::anonymize::Anonymize for #ident {
+            fn anonymize(&mut self, anonymizer: &mut protobuf_gen::anonymize::Anonymizer) {
+                #(#statements)*
+            }
+
+            #unique_key
+        }
+    })
+}
+
+fn generate_enum(item_enum: &ItemEnum) -> syn::Result<TokenStream> {
+    let ident = &item_enum.ident;
+    if let Some(lit_str) = unique_by(&item_enum.attrs) {
+        return Err(Diagnostic::InvalidAttribute
+            .error(lit_str.span(), "unique_by is only supported for 'struct'"));
+    }
+
+    let arms = item_enum.variants.iter().map(|variant| {
+        let variant_ident = &variant.ident;
+        match &variant.fields {
+            Fields::Named(fields_named) => {
+                let bindings = fields_named.named.iter().map(|field| &field.ident);
+                let statements = fields_named.named.iter().map(|field| {
+                    let field_ident = field.ident.as_ref().unwrap();
+                    anonymize_field(field, quote!(#field_ident))
+                });
+                quote!(#ident::#variant_ident { #(#bindings),* } => { #(#statements)* })
+            }
+            Fields::Unnamed(_) => quote! {
+                #ident::#variant_ident(inner) => {
+                    protobuf_gen::anonymize::Anonymize::anonymize(inner, anonymizer)
+                }
+            },
+            Fields::Unit => quote!(#ident::#variant_ident => {}),
+        }
+    });
+
+    Ok(quote! {
+        impl protobuf_gen::anonymize::Anonymize for #ident {
+            #[allow(unused_variables)]
+            fn anonymize(&mut self, anonymizer: &mut protobuf_gen::anonymize::Anonymizer) {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+fn unique_by(attrs: &[Attribute]) -> Option<syn::LitStr> {
+    match syn_util::get_attribute_value::<syn::Lit>(attrs, &["protobuf_gen", "unique_by"]) {
+        Some(syn::Lit::Str(lit_str)) => Some(lit_str),
+        _ => None,
+    }
+}
+
+fn anonymize_field(field: &Field, place: TokenStream) -> TokenStream {
+    if syn_util::contains_attribute(&field.attrs, &["protobuf_gen", "sensitive"]) {
+        quote!(protobuf_gen::anonymize::Sensitive::randomize(#place, anonymizer);)
+    } else if ["substitute", "encrypt"].iter().any(|key| {
+        syn_util::get_attribute_value::<String>(&field.attrs, &["protobuf_gen", key]).is_some()
+    }) {
+        // Their types are opaque to the schema, so they are kept as they are.
+        TokenStream::default()
+    } else {
+        quote!(protobuf_gen::anonymize::Anonymize::anonymize(#place, anonymizer);)
+    }
+}
@@ -1,16 +1,43 @@
 use std::collections::HashSet;
+use std::convert::TryFrom;
 
 use heck::SnakeCase;
 use proc_macro2::TokenStream;
 use quote::quote;
-use quote::ToTokens;
-use syn::{self, Fields, FieldsNamed, Ident, ItemEnum, ItemStruct, Type, TypePath, Variant};
-
-use crate::extract::Extract;
+use syn::spanned::Spanned;
+use syn::{
+    self, Fields, FieldsNamed, FieldsUnnamed, GenericArgument, Ident, Item, ItemEnum, ItemStruct,
+    PathArguments, Type, TypePath, Variant,
+};
+
+use crate::extract::diagnostic::Diagnostic;
+use crate::extract::index::{TypeIndex, INDEX_FILE};
+use crate::extract::settings::Settings;
+use crate::extract::status::Code;
+use crate::extract::{Extract, OptionalEnum};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Conversion {
+    IntoProxy,
+    FromProxy,
+    /// Converts from the proxy, taking unset message fields from `Self::default()`.
+    FromProxyOrDefault,
+}
 
 pub(crate) struct ConversionGenerator {
     pub(crate) token_stream: TokenStream,
     pub(crate) proxy_mod: TypePath,
+    pub(crate) doc_hidden: Option<bool>,
+    /// Whether `to_proxy` and `from_proxy` methods are generated.
+    pub(crate) inherent: bool,
+    /// Whether `TryInto` and `TryFrom` impls between the type and its proxy are generated.
+    pub(crate) trait_impls: bool,
+    /// Whether unset message fields and `oneof`s are decoded as in `Default::default()`.
+    pub(crate) default: bool,
+    pub(crate) settings: Settings,
+    /// The package and file of each type, to name them in docs and to find the types with the
+    /// inherent methods only.
+    pub(crate) index: Option<TypeIndex>,
 }
 
 impl Extract for ConversionGenerator {
@@ -20,11 +47,29 @@ impl Extract for ConversionGenerator {
         fields_named: &FieldsNamed,
     ) {
         let ident = &item_struct.ident;
-        let proxy = &self.proxy_mod;
-
-        let (ref bindings, ref assignments) = self.generate_assignments(fields_named);
+        let proxy = self.proxy_mod.clone();
+
+        let (ref bindings, ref into_assignments, _) =
+            self.generate_assignments(fields_named, Conversion::IntoProxy);
+        let from =
+            if self.default { Conversion::FromProxyOrDefault } else { Conversion::FromProxy };
+        let (_, ref from_assignments, ref defaults) = self.generate_assignments(fields_named, from);
+        let message = self.proxy_path(ident);
+        let doc_into = self.doc(format!("Converts into the protobuf message {}.", message));
+        let doc_into_optional = self.doc(format!(
+            "Converts into the protobuf message {}, as the value of a message field.",
+            message
+        ));
+
+        let into = quote! {
+            let #ident { #(#bindings)* .. } = self;
+            Ok(#proxy::#ident {
+                #(#into_assignments)*
+            })
+        };
 
-        self.token_stream.extend(quote! {
+        self.add_trait_impls(quote! {
+            #doc_into_optional
             impl ::std::convert::TryInto<Option<#proxy::#ident>> for #ident {
                 type Error = protobuf_gen::Error;
 
@@ -33,23 +78,11 @@ impl Extract for ConversionGenerator {
 
                     let #ident { #(#bindings)* .. } = self;
                     Ok(Some(#proxy::#ident {
-                        #(#assignments)*
+                        #(#into_assignments)*
                     }))
                 }
             }
 
-            impl ::std::convert::TryInto<#proxy::#ident> for #ident {
-                type Error = protobuf_gen::Error;
-
-                fn try_into(self) -> ::std::result::Result<#proxy::#ident, Self::Error> {
-                    use std::convert::TryInto;
-
-                    let #ident { #(#bindings)* .. } = self;
-                    Ok(#proxy::#ident {
-                        #(#assignments)*
-                    })
-                }
-            }
         });
 
         let private_fields = if let Fields::Named(FieldsNamed { named, .. }) = &item_struct.fields {
@@ -66,8 +99,15 @@ impl Extract for ConversionGenerator {
             Vec::new()
         };
         let private_fields = &private_fields;
-
-        self.token_stream.extend(quote! {
+        let doc_from = self.doc(format!("Converts from the protobuf message {}.", message));
+        let doc_from_optional = self.doc(format!(
+            "Converts from the value of a message field of the protobuf message {}, which fails \
+             if the field is not set.",
+            message
+        ));
+
+        self.add_trait_impls(quote! {
+            #doc_from_optional
             impl ::std::convert::TryFrom<Option<#proxy::#ident>> for #ident {
                 type Error = protobuf_gen::Error;
 
@@ -78,27 +118,24 @@ impl Extract for ConversionGenerator {
                         .ok_or_else(|| protobuf_gen::Error::new_empty_object(stringify!(#proxy::#ident)))?
                         .try_into().map_err(|e| protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e))?;
 
+                    #defaults
                     Ok(Self {
-                        #(#assignments)*
-                        #(#private_fields)*
-                    })
-                }
-            }
-
-            impl ::std::convert::TryFrom<#proxy::#ident> for #ident {
-                type Error = protobuf_gen::Error;
-
-                fn try_from(#proxy::#ident { #(#bindings)* }: #proxy::#ident) -> ::std::result::Result<Self, Self::Error> {
-                    use std::convert::TryInto;
-
-                    Ok(Self {
-                        #(#assignments)*
+                        #(#from_assignments)*
                         #(#private_fields)*
                     })
                 }
             }
         });
 
+        let from = quote! {
+            let #proxy::#ident { #(#bindings)* } = other;
+            #defaults
+            Ok(Self {
+                #(#from_assignments)*
+                #(#private_fields)*
+            })
+        };
+        self.add_proxy_conversions(ident, doc_into, into, doc_from, from);
         self.add_derive_protobuf_gen(ident);
     }
 
@@ -114,9 +151,15 @@ impl Extract for ConversionGenerator {
         let variant = &variant.ident;
         let variant_inner: Ident = syn::parse_str(&format!("{}Inner", variant)).unwrap();
 
-        let (bindings, assignments) = self.generate_assignments(fields_named);
+        let (bindings, assignments, _) =
+            self.generate_assignments(fields_named, Conversion::FromProxy);
+        let doc = self.doc(format!(
+            "Converts from the nested protobuf message `{}.{}` into `{}::{}`.",
+            ident, variant_inner, ident, variant
+        ));
 
         self.token_stream.extend(quote! {
+            #doc
             impl ::std::convert::TryFrom<#proxy::#inner_mod::#variant_inner> for #ident {
                 type Error = protobuf_gen::Error;
 
@@ -137,8 +180,13 @@ impl Extract for ConversionGenerator {
         let inner_mod: Ident = syn::parse_str(&ident.to_string().to_snake_case()).unwrap();
         let variant = &variant.ident;
         let variant_inner: Ident = syn::parse_str(&format!("{}Inner", variant)).unwrap();
+        let doc = self.doc(format!(
+            "Converts from the nested protobuf message `{}.{}` into `{}::{}`.",
+            ident, variant_inner, ident, variant
+        ));
 
         self.token_stream.extend(quote! {
+            #doc
             impl ::std::convert::TryFrom<#proxy::#inner_mod::#variant_inner> for #ident {
                 type Error = protobuf_gen::Error;
 
@@ -151,7 +199,7 @@ impl Extract for ConversionGenerator {
 
     fn extract_one_of(&mut self, item_enum: &ItemEnum) {
         let ident = &item_enum.ident;
-        let proxy = &self.proxy_mod;
+        let proxy = self.proxy_mod.clone();
         let inner_mod: Ident = syn::parse_str(&ident.to_string().to_snake_case()).unwrap();
 
         let cases = item_enum.variants.iter().map(|v| {
@@ -164,7 +212,8 @@ impl Extract for ConversionGenerator {
                     },
                 },
                 Fields::Named(fields_named) => {
-                    let (bindings, assignments) = self.generate_assignments(fields_named);
+                    let (bindings, assignments, _) =
+                        self.generate_assignments(fields_named, Conversion::IntoProxy);
                     quote!{
                         #ident::#variant { #(#bindings)* } => #proxy::#ident {
                             inner: Some(#proxy::#inner_mod::Inner::#variant(#proxy::#inner_mod::#variant_inner {
@@ -173,30 +222,34 @@ impl Extract for ConversionGenerator {
                         },
                     }
                 },
-                Fields::Unnamed(_) => quote!{
-                    #ident::#variant(inner) => #proxy::#ident {
-                        inner: Some(#proxy::#inner_mod::Inner::#variant(
-                            inner.try_into().map_err(|e| protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e))?
-                        )),
-                    },
+                Fields::Unnamed(fields_unnamed) => {
+                    let convert = self.variant_conversion(fields_unnamed, Conversion::IntoProxy);
+                    quote!{
+                        #ident::#variant(inner) => #proxy::#ident {
+                            inner: Some(#proxy::#inner_mod::Inner::#variant(
+                                #convert(inner).map_err(|e| protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e))?
+                            )),
+                        },
+                    }
                 },
             }
         }).collect::<Vec<_>>();
         let cases = &cases;
+        let message = self.proxy_path(ident);
+        let doc_into = self.doc(format!("Converts into the protobuf message {}.", message));
+        let doc_into_optional = self.doc(format!(
+            "Converts into the protobuf message {}, as the value of a message field.",
+            message
+        ));
+
+        let into = quote! {
+            Ok(match self {
+                #(#cases)*
+            })
+        };
 
-        self.token_stream.extend(quote! {
-            impl ::std::convert::TryInto<#proxy::#ident> for #ident {
-                type Error = protobuf_gen::Error;
-
-                fn try_into(self) -> ::std::result::Result<#proxy::#ident, Self::Error> {
-                    use std::convert::TryInto;
-
-                    Ok(match self {
-                        #(#cases)*
-                    })
-                }
-            }
-
+        self.add_trait_impls(quote! {
+            #doc_into_optional
             impl ::std::convert::TryInto<Option<#proxy::#ident>> for #ident {
                 type Error = protobuf_gen::Error;
 
@@ -215,26 +268,65 @@ impl Extract for ConversionGenerator {
             .iter()
             .map(|v| {
                 let variant = &v.ident;
-                quote!(#proxy::#inner_mod::Inner::#variant(inner) =>
-                    inner.try_into().map_err(|e| protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e)),
-                )
+                if let Fields::Unnamed(fields_unnamed) = &v.fields {
+                    let convert = self.variant_conversion(fields_unnamed, Conversion::FromProxy);
+                    quote!(#proxy::#inner_mod::Inner::#variant(inner) =>
+                        Ok(#ident::#variant(
+                            #convert(inner).map_err(|e| protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e))?
+                        )),
+                    )
+                } else {
+                    quote!(#proxy::#inner_mod::Inner::#variant(inner) =>
+                        inner.try_into().map_err(|e| protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e)),
+                    )
+                }
             })
             .collect::<Vec<_>>();
         let cases = &cases;
-
-        self.token_stream.extend(quote! {
-            impl ::std::convert::TryFrom<#proxy::#ident> for #ident {
-                type Error = protobuf_gen::Error;
-
-                fn try_from(#proxy::#ident { inner }: #proxy::#ident) -> ::std::result::Result<Self, Self::Error> {
-                    use std::convert::TryInto;
-
-                    match inner.ok_or_else(|| protobuf_gen::Error::new_empty_object(stringify!(#ident).to_string()))? {
-                        #(#cases)*
+        let doc_from = if self.default {
+            self.doc(format!(
+                "Converts from the protobuf message {}, which is `Default::default()` if none of \
+                 its `inner` oneof fields is set.",
+                message
+            ))
+        } else {
+            self.doc(format!(
+                "Converts from the protobuf message {}, which fails if none of its `inner` oneof \
+                 fields is set.",
+                message
+            ))
+        };
+        let unwrap_inner = |name: TokenStream| {
+            if self.default {
+                quote! {
+                    match inner {
+                        Some(inner) => inner,
+                        None => return Ok(<Self as Default>::default()),
                     }
                 }
+            } else {
+                quote!(inner
+                    .ok_or_else(|| protobuf_gen::Error::new_empty_object(stringify!(#name)))?)
+            }
+        };
+        let inner = unwrap_inner(quote!(#ident));
+        let inner_optional = unwrap_inner(quote!(#proxy::#ident));
+        let doc_from_optional = self.doc(format!(
+            "Converts from the value of a message field of the protobuf message {}, which fails \
+             if the field is not set.",
+            message
+        ));
+
+        let from = quote! {
+            let #proxy::#ident { inner } = other;
+            match #inner {
+                #(#cases)*
             }
+        };
+        self.add_proxy_conversions(ident, doc_into, into, doc_from, from);
 
+        self.add_trait_impls(quote! {
+            #doc_from_optional
             impl ::std::convert::TryFrom<Option<#proxy::#ident>> for #ident {
                 type Error = protobuf_gen::Error;
 
@@ -244,7 +336,7 @@ impl Extract for ConversionGenerator {
                     let #proxy::#ident { inner } = other
                         .ok_or_else(|| protobuf_gen::Error::new_empty_object(stringify!(#proxy::#ident)))?
                         .try_into().map_err(|e| protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e))?;
-                    match inner.ok_or_else(|| protobuf_gen::Error::new_empty_object(stringify!(#proxy::#ident)))? {
+                    match #inner_optional {
                         #(#cases)*
                     }
                 }
@@ -254,96 +346,223 @@ impl Extract for ConversionGenerator {
         self.add_derive_protobuf_gen(ident);
     }
 
-    fn extract_enumerator(&mut self, item_enum: &ItemEnum) {
+    fn extract_optional_enum(&mut self, item_enum: &ItemEnum, optional_enum: &OptionalEnum) {
         let ident = &item_enum.ident;
-        let proxy = &self.proxy_mod;
-
-        let cases = item_enum.variants.iter().map(|v| {
-            let variant = &v.ident;
-            quote!(#ident::#variant => #proxy::#ident::#variant,)
-        });
-
+        let none = &optional_enum.none.ident;
+        let some = &optional_enum.some.ident;
+        let ty = optional_enum.ty;
+        let doc_into = self.doc(format!(
+            "Converts into the value of an optional field, which is not set for `{}::{}`.",
+            ident, none
+        ));
+        let doc_from = self.doc(format!(
+            "Converts from the value of an optional field, which is `{}::{}` if the field is not \
+             set.",
+            ident, none
+        ));
+
+        // generic over the proxy type of `ty`, which is only known to the field of the message.
         self.token_stream.extend(quote! {
-            impl ::std::convert::TryInto<#proxy::#ident> for #ident {
+            #doc_into
+            impl<P> ::std::convert::TryInto<Option<P>> for #ident
+            where
+                #ty: ::std::convert::TryInto<P>,
+                <#ty as ::std::convert::TryInto<P>>::Error:
+                    Into<Box<dyn ::std::error::Error + Sync + Send + 'static>>,
+            {
                 type Error = protobuf_gen::Error;
 
-                fn try_into(self) -> ::std::result::Result<#proxy::#ident, Self::Error> {
-                    Ok(match self {
-                        #(#cases)*
-                    })
+                fn try_into(self) -> ::std::result::Result<Option<P>, Self::Error> {
+                    use std::convert::TryInto;
+
+                    match self {
+                        #ident::#none => Ok(None),
+                        #ident::#some(inner) => inner.try_into().map(Some).map_err(|e| {
+                            protobuf_gen::Error::new_try_from_error(stringify!(#ident), e)
+                        }),
+                    }
                 }
             }
 
-            impl ::std::convert::TryInto<i32> for #ident {
+            #doc_from
+            impl<P> ::std::convert::TryFrom<Option<P>> for #ident
+            where
+                #ty: ::std::convert::TryFrom<P>,
+                <#ty as ::std::convert::TryFrom<P>>::Error:
+                    Into<Box<dyn ::std::error::Error + Sync + Send + 'static>>,
+            {
                 type Error = protobuf_gen::Error;
 
-                fn try_into(self) -> ::std::result::Result<i32, Self::Error> {
-                    let proxy: #proxy::#ident = self.try_into().map_err(|e| {
-                        protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e)
-                    })?;
+                fn try_from(other: Option<P>) -> ::std::result::Result<Self, Self::Error> {
+                    use std::convert::TryFrom;
 
-                    Ok(proxy.into())
+                    match other {
+                        None => Ok(#ident::#none),
+                        Some(inner) => <#ty>::try_from(inner).map(#ident::#some).map_err(|e| {
+                            protobuf_gen::Error::new_try_from_error(stringify!(#ident), e)
+                        }),
+                    }
                 }
             }
         });
+    }
+
+    fn extract_enumerator(&mut self, item_enum: &ItemEnum) {
+        let ident = &item_enum.ident;
+        let proxy = self.proxy_mod.clone();
 
         let cases = item_enum.variants.iter().map(|v| {
             let variant = &v.ident;
-            quote!(#proxy::#ident::#variant => #ident::#variant,)
+            quote!(#ident::#variant => #proxy::#ident::#variant,)
+        });
+        let i32_cases = item_enum.variants.iter().map(|v| {
+            let variant = &v.ident;
+            quote!(#ident::#variant => #proxy::#ident::#variant as i32,)
         });
+        let enumeration = self.proxy_path(ident);
+        let doc_into = self.doc(format!("Converts into the protobuf enum {}.", enumeration));
+        let doc_into_i32 =
+            self.doc(format!("Converts into the wire value of the protobuf enum {}.", enumeration));
+
+        let into = quote! {
+            Ok(match self {
+                #(#cases)*
+            })
+        };
 
         self.token_stream.extend(quote! {
-            impl ::std::convert::TryFrom<#proxy::#ident> for #ident {
-                type Error = protobuf_gen::Error;
-
-                fn try_from(other: #proxy::#ident) -> ::std::result::Result<Self, Self::Error> {
-                    Ok(match other {
-                        #(#cases)*
-                    })
+            #doc_into_i32
+            impl ::std::convert::From<#ident> for i32 {
+                fn from(other: #ident) -> i32 {
+                    match other {
+                        #(#i32_cases)*
+                    }
                 }
             }
+        });
+
+        let cases = item_enum.variants.iter().map(|v| {
+            let variant = &v.ident;
+            quote!(#proxy::#ident::#variant => #ident::#variant,)
+        });
+        let doc_from = self.doc(format!("Converts from the protobuf enum {}.", enumeration));
+        let doc_from_i32 = self.doc(format!(
+            "Converts from the wire value of the protobuf enum {}, which fails if the value is \
+             unknown.",
+            enumeration
+        ));
+
+        let from = quote! {
+            Ok(match other {
+                #(#cases)*
+            })
+        };
+        self.add_proxy_conversions(ident, doc_into, into, doc_from, from);
 
+        let variants: Vec<_> = item_enum.variants.iter().map(|v| &v.ident).collect();
+        self.token_stream.extend(quote! {
+            #doc_from_i32
             impl ::std::convert::TryFrom<i32> for #ident {
                 type Error = protobuf_gen::Error;
 
                 fn try_from(n: i32) -> ::std::result::Result<Self, Self::Error> {
-                    let proxy = #proxy::#ident::from_i32(n)
-                        .ok_or_else(|| protobuf_gen::Error::new_invalid_ident(stringify!(#proxy::#ident)))?;
-                    proxy.try_into().map_err(|e| {
-                        protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e)
-                    })
+                    match n {
+                        #(n if n == #proxy::#ident::#variants as i32 => Ok(#ident::#variants),)*
+                        _ => Err(protobuf_gen::Error::new_invalid_ident(stringify!(#proxy::#ident))),
+                    }
                 }
             }
         });
 
+        let names: Vec<_> = item_enum
+            .variants
+            .iter()
+            .map(|v| self.settings.enum_value_name(&ident.to_string(), &v.ident.to_string()))
+            .collect();
+        let doc_names = self.doc(format!(
+            "The names of the values in the protobuf enum {}, in the order of their numbers.",
+            enumeration
+        ));
+        let doc_as_name = self
+            .doc(format!("Returns the name of the value in the protobuf enum {}.", enumeration));
+        let doc_from_name = self.doc(format!(
+            "Returns the value named `name` in the protobuf enum {}, if any.",
+            enumeration
+        ));
+
+        self.token_stream.extend(quote! {
+            impl #ident {
+                #doc_names
+                pub const PROTO_NAMES: &'static [&'static str] = &[#(#names),*];
+
+                #doc_as_name
+                pub fn as_proto_name(&self) -> &'static str {
+                    match self {
+                        #(#ident::#variants => #names,)*
+                    }
+                }
+
+                #doc_from_name
+                pub fn from_proto_name(name: &str) -> Option<Self> {
+                    match name {
+                        #(#names => Some(#ident::#variants),)*
+                        _ => None,
+                    }
+                }
+            }
+        });
+
+        let (doc_impl, doc_to, doc_from) = self.protobuf_gen_docs(&enumeration);
+        let to_proxy = self.conversion_to_proxy(ident);
+        let from_proxy = self.conversion_from_proxy(ident);
         self.token_stream.extend(quote! {
-            impl ProtobufGen for #ident {
+            #doc_impl
+            impl protobuf_gen::ProtobufGen for #ident {
                 type Error = protobuf_gen::Error;
 
+                #doc_to
                 fn to_protobuf<W: ::std::io::Write>(self, w: &mut W) -> ::std::result::Result<(), Self::Error> {
                     use std::convert::TryInto;
                     use prost::Message;
 
-                    let proxy: #proxy::#ident = self.try_into().map_err(|e| {
+                    let mut buffer = Vec::new();
+                    let result = #to_proxy(self).map_err(|e| {
                         protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e)
-                    })?;
-                    let proxy: i32 = proxy.into();
-                    let mut buffer = Vec::with_capacity(proxy.encoded_len());
-                    proxy.encode(&mut buffer)?;
-                    w.write_all(&buffer)?;
-                    Ok(())
+                    }).and_then(|proxy: #proxy::#ident| {
+                        let proxy: i32 = proxy.into();
+                        buffer.reserve(proxy.encoded_len());
+                        proxy.encode(&mut buffer)?;
+                        w.write_all(&buffer)?;
+                        Ok(())
+                    });
+                    protobuf_gen::observer::notify(
+                        stringify!(#ident),
+                        protobuf_gen::Direction::Encode,
+                        buffer.len(),
+                        result.is_ok(),
+                    );
+                    result
                 }
 
+                #doc_from
                 fn from_protobuf<R: ::std::io::Read>(r: &mut R) -> ::std::result::Result<Self, Self::Error> {
                     use std::convert::TryInto;
 
                     let mut buffer = Vec::new();
-                    r.read_to_end(&mut buffer)?;
-                    let proxy = #proxy::#ident::from_i32(prost::Message::decode(&buffer[..])?)
-                        .ok_or_else(|| protobuf_gen::Error::new_invalid_ident(stringify!(#ident).to_string()))?;
-                    proxy.try_into().map_err(|e| {
-                        protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e)
-                    })
+                    let result = r.read_to_end(&mut buffer).map_err(protobuf_gen::Error::from).and_then(|_| {
+                        let proxy = #proxy::#ident::from_i32(prost::Message::decode(&buffer[..])?)
+                            .ok_or_else(|| protobuf_gen::Error::new_invalid_ident(stringify!(#ident)))?;
+                        #from_proxy(proxy).map_err(|e| {
+                            protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e)
+                        })
+                    });
+                    protobuf_gen::observer::notify(
+                        stringify!(#ident),
+                        protobuf_gen::Direction::Decode,
+                        buffer.len(),
+                        result.is_ok(),
+                    );
+                    result
                 }
             }
         });
@@ -351,48 +570,558 @@ impl Extract for ConversionGenerator {
 }
 
 impl ConversionGenerator {
-    fn add_derive_protobuf_gen<T>(&mut self, ident: T)
-    where
-        T: ToTokens,
-    {
+    fn doc(&self, text: String) -> TokenStream {
+        match self.doc_hidden {
+            Some(true) => quote!(#[doc(hidden)]),
+            Some(false) => quote!(#[doc = #text]),
+            None => TokenStream::default(),
+        }
+    }
+
+    /// Whether the values of `field` have the inherent methods only, either by
+    /// `#[protobuf_gen(inherent)]` on the field or as recorded in the index for their type.
+    fn is_inherent(&self, field: &syn::Field) -> bool {
+        if syn_util::contains_attribute(&field.attrs, &["protobuf_gen", "inherent"]) {
+            return true;
+        }
+        let ty = match &field.ty {
+            Type::Path(type_path) => value_type(type_path).unwrap_or(&field.ty),
+            ty => ty,
+        };
+        match (ty, &self.index) {
+            (Type::Path(type_path), Some(index)) => type_path
+                .path
+                .segments
+                .last()
+                .and_then(|segment| index.get(&segment.ident.to_string()))
+                .is_some_and(|ty| ty.inherent),
+            _ => false,
+        }
+    }
+
+    // The conversion of the single unnamed field of a variant of a `oneof`, whose proxy is not an
+    // `Option`.
+    fn variant_conversion(
+        &self,
+        fields_unnamed: &FieldsUnnamed,
+        conversion: Conversion,
+    ) -> TokenStream {
+        let field = &fields_unnamed.unnamed[0];
+        value_conversion(self.is_inherent(field), &field.ty, conversion)
+    }
+
+    fn proxy_path(&self, ident: &Ident) -> String {
+        let segments: Vec<_> =
+            self.proxy_mod.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+        match self.index.as_ref().and_then(|index| index.get(&ident.to_string())) {
+            Some(ty) => format!(
+                "`{}` of `{}` (`{}::{}`)",
+                ty.full_name(&ident.to_string()),
+                ty.path.display(),
+                segments.join("::"),
+                ident
+            ),
+            None => format!("`{}` (`{}::{}`)", ident, segments.join("::"), ident),
+        }
+    }
+
+    fn protobuf_gen_docs(&self, proto: &str) -> (TokenStream, TokenStream, TokenStream) {
+        (
+            self.doc(format!("Encodes and decodes as the protobuf {}.", proto)),
+            self.doc(format!("Writes the protobuf encoding of {} to `w`.", proto)),
+            self.doc(format!("Reads the protobuf encoding of {} from all of `r`.", proto)),
+        )
+    }
+
+    pub(crate) fn add_schema_version(&mut self, ident: &Ident, version: u64) -> syn::Result<()> {
+        let version = u32::try_from(version).map_err(|_| {
+            Diagnostic::InvalidAttribute.error(ident.span(), "schema_version must fit in u32")
+        })?;
+
+        let doc = self.doc(format!(
+            "The `schema_version` of {}, emitted as its `(protobuf_gen.schema_version)` option.",
+            self.proxy_path(ident)
+        ));
+
+        self.token_stream.extend(quote! {
+            impl #ident {
+                #doc
+                pub const SCHEMA_VERSION: u32 = #version;
+            }
+        });
+        Ok(())
+    }
+
+    pub(crate) fn add_ffi(&mut self, ident: &Ident) -> syn::Result<()> {
+        let name = ident.to_string();
+        let prefix = match self.index.as_ref().map(|index| (index.get(&name), index)) {
+            Some((Some(ty), _)) => ty.ffi_prefix(&name),
+            Some((None, index)) if index.find(&name).count() > 1 => {
+                let packages: Vec<_> = index.find(&name).map(|ty| ty.package.as_str()).collect();
+                return Err(Diagnostic::InvalidAttribute.error(
+                    ident.span(),
+                    format!(
+                        "`ffi` needs the package of the type, but `{}` is in several packages: {}",
+                        name,
+                        packages.join(", ")
+                    ),
+                ));
+            }
+            Some((None, _)) => {
+                return Err(Diagnostic::InvalidAttribute.error(
+                    ident.span(),
+                    format!(
+                        "`ffi` needs the package of the type, but `{}` is not in the schemas \
+                         generated by the build script; add its source to the `Config`",
+                        name
+                    ),
+                ))
+            }
+            None => {
+                return Err(Diagnostic::InvalidAttribute.error(
+                    ident.span(),
+                    format!(
+                        "`ffi` needs the package of the type, read from `{}` in `OUT_DIR`, which \
+                         `Config::generate` writes when it runs in the build script of the crate",
+                        INDEX_FILE
+                    ),
+                ))
+            }
+        };
+        let decode = Ident::new(&format!("{}_decode", prefix), ident.span());
+        let encode = Ident::new(&format!("{}_encode", prefix), ident.span());
+        let free = Ident::new(&format!("{}_free", prefix), ident.span());
+
+        self.token_stream.extend(quote! {
+            /// Decodes `len` bytes at `data`, returning null on failure or on a panic.
+            ///
+            /// # Safety
+            ///
+            /// `data` must point to `len` readable bytes.
+            #[no_mangle]
+            pub unsafe extern "C" fn #decode(data: *const u8, len: usize) -> *mut #ident {
+                protobuf_gen::ffi::decode(data, len)
+            }
+
+            /// Encodes `*value` into `*out`, which is freed with `protobuf_gen_buffer_free`.
+            ///
+            /// # Safety
+            ///
+            /// `value` must point to a valid value and `out` must be writable.
+            #[no_mangle]
+            pub unsafe extern "C" fn #encode(value: *const #ident, out: *mut protobuf_gen::ffi::Buffer) -> protobuf_gen::ffi::Status {
+                protobuf_gen::ffi::encode(value, out)
+            }
+
+            /// # Safety
+            ///
+            /// `value` must be returned by the decode function, and must not be freed twice.
+            #[no_mangle]
+            pub unsafe extern "C" fn #free(value: *mut #ident) {
+                protobuf_gen::ffi::free(value)
+            }
+        });
+        Ok(())
+    }
+
+    /// Emits trait impls between the type and its proxy, unless the type only has inherent
+    /// conversions.
+    fn add_trait_impls(&mut self, token_stream: TokenStream) {
+        if self.trait_impls {
+            self.token_stream.extend(token_stream);
+        }
+    }
+
+    /// Emits the conversions between `ident` and its proxy type as `TryInto`/`TryFrom` impls
+    /// and/or `to_proxy`/`from_proxy` methods. `into` converts `self`, and `from` converts
+    /// `other`.
+    fn add_proxy_conversions(
+        &mut self,
+        ident: &Ident,
+        doc_into: TokenStream,
+        into: TokenStream,
+        doc_from: TokenStream,
+        from: TokenStream,
+    ) {
+        let proxy = &self.proxy_mod;
+
+        if self.trait_impls {
+            self.token_stream.extend(quote! {
+                #doc_into
+                impl ::std::convert::TryInto<#proxy::#ident> for #ident {
+                    type Error = protobuf_gen::Error;
+
+                    fn try_into(self) -> ::std::result::Result<#proxy::#ident, Self::Error> {
+                        use std::convert::TryInto;
+
+                        #into
+                    }
+                }
+
+                #doc_from
+                impl ::std::convert::TryFrom<#proxy::#ident> for #ident {
+                    type Error = protobuf_gen::Error;
+
+                    fn try_from(other: #proxy::#ident) -> ::std::result::Result<Self, Self::Error> {
+                        use std::convert::TryInto;
+
+                        #from
+                    }
+                }
+            });
+        }
+
+        if self.inherent {
+            self.token_stream.extend(quote! {
+                impl #ident {
+                    #doc_into
+                    pub fn to_proxy(self) -> ::std::result::Result<#proxy::#ident, protobuf_gen::Error> {
+                        use std::convert::TryInto;
+
+                        #into
+                    }
+
+                    #doc_from
+                    pub fn from_proxy(other: #proxy::#ident) -> ::std::result::Result<Self, protobuf_gen::Error> {
+                        use std::convert::TryInto;
+
+                        #from
+                    }
+                }
+            });
+        }
+    }
+
+    fn conversion_to_proxy(&self, ident: &Ident) -> TokenStream {
+        let proxy = &self.proxy_mod;
+        if self.inherent {
+            quote!(#ident::to_proxy)
+        } else {
+            quote!(<#ident as ::std::convert::TryInto<#proxy::#ident>>::try_into)
+        }
+    }
+
+    fn conversion_from_proxy(&self, ident: &Ident) -> TokenStream {
+        let proxy = &self.proxy_mod;
+        if self.inherent {
+            quote!(#ident::from_proxy)
+        } else {
+            quote!(<#ident as ::std::convert::TryFrom<#proxy::#ident>>::try_from)
+        }
+    }
+
+    pub(crate) fn add_wire_struct(&mut self, item: &Item) -> syn::Result<()> {
+        const SCALARS: &[(&str, usize)] = &[
+            ("bool", 1),
+            ("u8", 1),
+            ("i8", 1),
+            ("u16", 2),
+            ("i16", 2),
+            ("u32", 4),
+            ("i32", 4),
+            ("f32", 4),
+            ("u64", 8),
+            ("i64", 8),
+            ("f64", 8),
+        ];
+
+        let item_struct = match item {
+            Item::Struct(item_struct) => item_struct,
+            Item::Enum(item_enum) => {
+                return Err(Diagnostic::InvalidAttribute
+                    .error(item_enum.ident.span(), "wire_struct is only supported for 'struct'"))
+            }
+            _ => unreachable!(),
+        };
+        let ident = &item_struct.ident;
+
+        let mut fields = Vec::new();
+        let mut private_fields = Vec::new();
+        for field in &item_struct.fields {
+            let field_ident = field.ident.as_ref().unwrap();
+            if !matches!(field.vis, syn::Visibility::Public(_))
+                && !syn_util::contains_attribute(&field.attrs, &["protobuf_gen", "expose"])
+            {
+                private_fields.push(quote!(#field_ident: Default::default(),));
+                continue;
+            }
+
+            let scalar = match &field.ty {
+                Type::Path(type_path) => type_path
+                    .path
+                    .get_ident()
+                    .and_then(|type_ident| SCALARS.iter().find(|(name, _)| type_ident == name)),
+                _ => None,
+            };
+            match scalar {
+                Some(&(name, len)) => fields.push((field_ident, name, len)),
+                None => {
+                    return Err(Diagnostic::InvalidAttribute.error(
+                        field.ty.span(),
+                        "wire_struct only supports fields of fixed-size scalar types",
+                    ))
+                }
+            }
+        }
+
+        let len = 1 + fields.iter().map(|(_, _, len)| len).sum::<usize>();
+        let mut offset = 1;
+        let mut layout =
+            String::from("| offset | field | type |\n|---|---|---|\n| 0 | (version) | `u8` |\n");
+        for (field_ident, name, len) in &fields {
+            layout += &format!("| {} | `{}` | `{}` |\n", offset, field_ident, name);
+            offset += len;
+        }
+        let doc_len = self.doc(format!("The size of the wire struct of `{}`.", ident));
+        let doc_to = self.doc(format!(
+            "Writes the wire struct of `{}`, see `protobuf_gen::wire_struct`:\n\n{}",
+            ident, layout
+        ));
+        let doc_from = self.doc(format!(
+            "Reads the wire struct of `{}` written by `to_wire_struct`, which fails if its \
+             version or its size does not match.",
+            ident
+        ));
+
+        let field_idents: Vec<_> = fields.iter().map(|(field_ident, _, _)| field_ident).collect();
+        self.token_stream.extend(quote! {
+            impl #ident {
+                #doc_len
+                pub const WIRE_STRUCT_LEN: usize = #len;
+
+                #doc_to
+                pub fn to_wire_struct(&self) -> Vec<u8> {
+                    let mut bytes = Vec::with_capacity(Self::WIRE_STRUCT_LEN);
+                    bytes.push(protobuf_gen::wire_struct::VERSION);
+                    #(protobuf_gen::wire_struct::WireScalar::put(self.#field_idents, &mut bytes);)*
+                    bytes
+                }
+
+                #doc_from
+                pub fn from_wire_struct(bytes: &[u8]) -> ::std::result::Result<Self, protobuf_gen::Error> {
+                    let mut bytes = protobuf_gen::wire_struct::payload(bytes, Self::WIRE_STRUCT_LEN, stringify!(#ident))?;
+                    Ok(Self {
+                        #(#field_idents: protobuf_gen::wire_struct::WireScalar::take(&mut bytes),)*
+                        #(#private_fields)*
+                    })
+                }
+            }
+        });
+        Ok(())
+    }
+
+    pub(crate) fn add_dispatch(&mut self, item: &Item, response: &syn::LitStr) -> syn::Result<()> {
+        let item_enum = match item {
+            Item::Enum(item_enum) => item_enum,
+            Item::Struct(ItemStruct { ident, .. }) => {
+                return Err(Diagnostic::InvalidAttribute
+                    .error(ident.span(), "dispatch is only supported for 'enum'"))
+            }
+            _ => unreachable!(),
+        };
+        let response: Type = response.parse().map_err(|_| {
+            Diagnostic::InvalidAttribute.error(
+                response.span(),
+                format!("invalid dispatch attribute: \"{}\"", response.value()),
+            )
+        })?;
+        let ident = &item_enum.ident;
+        let vis = &item_enum.vis;
+        let handler = Ident::new(&format!("{}Handler", ident), ident.span());
+
+        let mut methods = Vec::new();
+        let mut cases = Vec::new();
+        for variant in &item_enum.variants {
+            let variant_ident = &variant.ident;
+            let method =
+                Ident::new(&variant_ident.to_string().to_snake_case(), variant_ident.span());
+            match &variant.fields {
+                Fields::Named(fields_named) => {
+                    let fields: Vec<_> = fields_named.named.iter().map(|x| &x.ident).collect();
+                    let types = fields_named.named.iter().map(|x| &x.ty);
+                    methods.push(quote!(fn #method(&mut self, #(#fields: #types),*) -> #response;));
+                    cases.push(quote!(#ident::#variant_ident { #(#fields),* } => handler.#method(#(#fields),*),));
+                }
+                Fields::Unnamed(fields_unnamed) => {
+                    let ty = &fields_unnamed.unnamed[0].ty;
+                    methods.push(quote!(fn #method(&mut self, request: #ty) -> #response;));
+                    cases
+                        .push(quote!(#ident::#variant_ident(request) => handler.#method(request),));
+                }
+                Fields::Unit => {
+                    methods.push(quote!(fn #method(&mut self) -> #response;));
+                    cases.push(quote!(#ident::#variant_ident => handler.#method(),));
+                }
+            }
+        }
+
+        let doc_handler =
+            format!("Handles each variant of `{}`, as called by `{}::dispatch`.", ident, ident);
+        self.token_stream.extend(quote! {
+            #[doc = #doc_handler]
+            #vis trait #handler {
+                #(#methods)*
+            }
+
+            impl #ident {
+                /// Calls the method of `handler` for the variant, e.g. in `Plugin::handle`.
+                #vis fn dispatch<H: #handler + ?Sized>(self, handler: &mut H) -> #response {
+                    match self {
+                        #(#cases)*
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    pub(crate) fn add_status(&mut self, item: &Item, type_name: &str) -> syn::Result<()> {
+        // the details are the encoding of the error, so it has to be converted to a message.
+        let item_enum = match item {
+            Item::Enum(item_enum)
+                if OptionalEnum::new(item_enum).is_none()
+                    && item_enum.variants.iter().any(|v| !matches!(v.fields, Fields::Unit)) =>
+            {
+                item_enum
+            }
+            Item::Struct(ItemStruct { ident, .. }) | Item::Enum(ItemEnum { ident, .. }) => {
+                return Err(Diagnostic::InvalidAttribute.error(
+                    ident.span(),
+                    "status is only supported for 'enum' converted to 'message'",
+                ))
+            }
+            _ => unreachable!(),
+        };
+        let ident = &item_enum.ident;
+
+        let mut cases = Vec::new();
+        for variant in &item_enum.variants {
+            let code = match syn_util::get_attribute_value::<syn::Lit>(
+                &variant.attrs,
+                &["protobuf_gen", "code"],
+            ) {
+                Some(syn::Lit::Str(code)) => Some(code),
+                _ => None,
+            };
+            // the codes are checked by `diagnostic::check_item`
+            let code_ident = match &code {
+                Some(code) => {
+                    let found = Code::from_name(&code.value()).unwrap_or(Code::Unknown);
+                    Ident::new(&format!("{:?}", found), code.span())
+                }
+                None => Ident::new(&format!("{:?}", Code::Unknown), variant.ident.span()),
+            };
+            let variant = &variant.ident;
+            cases.push(quote!(#ident::#variant { .. } => protobuf_gen::status::Code::#code_ident,));
+        }
+
+        let doc_code = self.doc(format!("Returns the `google.rpc.Code` of the `{}`.", ident));
+        let doc_into = self.doc(format!(
+            "Converts into `google.rpc.Status` with `{}` packed in its details.",
+            type_name
+        ));
+        let doc_from = self.doc(format!(
+            "Converts from `google.rpc.Status` by unpacking `{}` from its details, which fails \
+             if it has none.",
+            type_name
+        ));
+
+        self.token_stream.extend(quote! {
+            impl #ident {
+                #doc_code
+                pub fn status_code(&self) -> protobuf_gen::status::Code {
+                    match self {
+                        #(#cases)*
+                    }
+                }
+            }
+
+            #doc_into
+            impl ::std::convert::From<#ident> for protobuf_gen::status::Status {
+                fn from(other: #ident) -> Self {
+                    let code = other.status_code();
+                    let message = ::std::string::ToString::to_string(&other);
+                    protobuf_gen::status::pack(code, message, other, #type_name)
+                }
+            }
+
+            #doc_from
+            impl ::std::convert::TryFrom<protobuf_gen::status::Status> for #ident {
+                type Error = protobuf_gen::Error;
+
+                fn try_from(other: protobuf_gen::status::Status) -> ::std::result::Result<Self, Self::Error> {
+                    protobuf_gen::status::unpack(&other, #type_name)
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn add_derive_protobuf_gen(&mut self, ident: &Ident) {
         let proxy = &self.proxy_mod;
+        let (doc_impl, doc_to, doc_from) = self.protobuf_gen_docs(&self.proxy_path(ident));
+        let to_proxy = self.conversion_to_proxy(ident);
+        let from_proxy = self.conversion_from_proxy(ident);
 
         self.token_stream.extend(quote! {
-            impl ProtobufGen for #ident {
+            #doc_impl
+            impl protobuf_gen::ProtobufGen for #ident {
                 type Error = protobuf_gen::Error;
 
+                #doc_to
                 fn to_protobuf<W: ::std::io::Write>(self, w: &mut W) -> ::std::result::Result<(), Self::Error> {
                     use std::convert::TryInto;
                     use prost::Message;
 
-                    let proxy: #proxy::#ident = self.try_into().map_err(|e| {
+                    let mut buffer = Vec::new();
+                    let result = #to_proxy(self).map_err(|e| {
                         protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e)
-                    })?;
-
-                    let mut buffer = Vec::with_capacity(proxy.encoded_len());
-                    proxy.encode(&mut buffer)?;
-                    w.write_all(&buffer)?;
-                    Ok(())
+                    }).and_then(|proxy: #proxy::#ident| {
+                        buffer.reserve(proxy.encoded_len());
+                        proxy.encode(&mut buffer)?;
+                        w.write_all(&buffer)?;
+                        Ok(())
+                    });
+                    protobuf_gen::observer::notify(
+                        stringify!(#ident),
+                        protobuf_gen::Direction::Encode,
+                        buffer.len(),
+                        result.is_ok(),
+                    );
+                    result
                 }
 
+                #doc_from
                 fn from_protobuf<R: ::std::io::Read>(r: &mut R) -> ::std::result::Result<Self, Self::Error> {
                     use std::convert::TryInto;
 
                     let mut buffer = Vec::new();
-                    r.read_to_end(&mut buffer)?;
-                    let proxy: #proxy::#ident = prost::Message::decode(&buffer[..])?;
-                    proxy.try_into().map_err(|e| {
-                        protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e)
-                    })
+                    let result = r.read_to_end(&mut buffer).map_err(protobuf_gen::Error::from).and_then(|_| {
+                        let proxy: #proxy::#ident = prost::Message::decode(&buffer[..])?;
+                        #from_proxy(proxy).map_err(|e| {
+                            protobuf_gen::Error::new_try_from_error(stringify!(#proxy::#ident), e)
+                        })
+                    });
+                    protobuf_gen::observer::notify(
+                        stringify!(#ident),
+                        protobuf_gen::Direction::Decode,
+                        buffer.len(),
+                        result.is_ok(),
+                    );
+                    result
                 }
             }
         });
     }
 
+    /// Returns the bindings of the fields, their assignments, and with
+    /// `Conversion::FromProxyOrDefault` the statements converting the fields which take the value
+    /// of `Self::default()` if they are not set.
     fn generate_assignments(
         &self,
         fields_named: &FieldsNamed,
-    ) -> (Vec<TokenStream>, Vec<TokenStream>) {
+        conversion: Conversion,
+    ) -> (Vec<TokenStream>, Vec<TokenStream>, TokenStream) {
         let bindings = fields_named
             .named
             .iter()
@@ -402,13 +1131,30 @@ impl ConversionGenerator {
             })
             .collect();
 
+        let mut defaulted = Vec::new();
         let assignments = fields_named
             .named
             .iter()
             .map(|x| {
                 let field = x.ident.as_ref().unwrap();
+                if let Some(key) =
+                    syn_util::get_attribute_value::<String>(&x.attrs, &["protobuf_gen", "encrypt"])
+                {
+                    let convert = match conversion {
+                        Conversion::IntoProxy => quote!(protobuf_gen::keyring::seal),
+                        Conversion::FromProxy | Conversion::FromProxyOrDefault => {
+                            quote!(protobuf_gen::keyring::open)
+                        }
+                    };
+                    return quote!(
+                        #field : #convert(#key, #field).map_err(|e| protobuf_gen::Error::new_try_from_error(stringify!(#field), e))?,
+                    );
+                }
+                let inherent = self.is_inherent(x);
                 if let Type::Path(type_path) = &x.ty {
                     let type_ident = &type_path.path.segments.last().unwrap().ident;
+                    let convert =
+                        value_conversion(inherent, value_type(type_path).unwrap_or(&x.ty), conversion);
                     if type_ident == "Vec"
                         || type_ident == "HashSet"
                         || type_ident == "IndexMap"
@@ -416,20 +1162,26 @@ impl ConversionGenerator {
                     {
                         return quote!(
                             #field : #field.into_iter().map(|x|
-                                x.try_into().map_err(|e|
-                                    protobuf_gen::Error::new_try_from_error(stringify!(#field).to_string(), e)
+                                #convert(x).map_err(|e|
+                                    protobuf_gen::Error::new_try_from_error(stringify!(#field), e)
                                 )
                             ).collect::<::std::result::Result<_, protobuf_gen::Error>>()?,
                         );
+                    } else if type_ident == "Option" {
+                        return quote!(
+                            #field : #field.map(#convert).transpose().map_err(|e|
+                                protobuf_gen::Error::new_try_from_error(stringify!(#field), e)
+                            )?,
+                        );
                     } else if type_ident == "HashMap" {
                         return quote!(
                             #field : #field.into_iter().map(|(k, v)| {
                                 let k = k.try_into().map_err(|e|
-                                    protobuf_gen::Error::new_try_from_error(stringify!(#field).to_string(), e)
+                                    protobuf_gen::Error::new_try_from_error(stringify!(#field), e)
                                 )?;
 
-                                let v = v.try_into().map_err(|e|
-                                    protobuf_gen::Error::new_try_from_error(stringify!(#field).to_string(), e)
+                                let v = #convert(v).map_err(|e|
+                                    protobuf_gen::Error::new_try_from_error(stringify!(#field), e)
                                 )?;
 
                                 Ok((k, v))
@@ -437,12 +1189,226 @@ impl ConversionGenerator {
                         );
                     }
                 }
+                // the proxy of a message field is an `Option`, which the inherent methods do not
+                // convert.
+                let convert = value_conversion(inherent, &x.ty, conversion);
+                let result = match (inherent, conversion) {
+                    (false, _) => quote!(#convert(#field)),
+                    (true, Conversion::IntoProxy) => quote!(#convert(#field).map(Some)),
+                    (true, Conversion::FromProxy) | (true, Conversion::FromProxyOrDefault) => quote!(
+                        #field
+                            .ok_or_else(|| protobuf_gen::Error::new_empty_object(stringify!(#field)))
+                            .and_then(#convert)
+                    ),
+                };
+                if conversion == Conversion::FromProxyOrDefault {
+                    defaulted.push((field, result));
+                    return quote!(
+                        #field : #field,
+                    );
+                }
                 quote!(
-                    #field : #field.try_into().map_err(|e| protobuf_gen::Error::new_try_from_error(stringify!(#field).to_string(), e))?,
+                    #field : #result.map_err(|e| protobuf_gen::Error::new_try_from_error(stringify!(#field), e))?,
                 )
             })
             .collect();
 
-        (bindings, assignments)
+        (bindings, assignments, generate_defaults(&defaulted))
+    }
+}
+
+// Converts `fields` with their conversions into `Option`s, and then takes the values of the unset
+// ones from `Self::default()`, which is built at most once.
+fn generate_defaults(fields: &[(&Ident, TokenStream)]) -> TokenStream {
+    if fields.is_empty() {
+        return TokenStream::default();
+    }
+    let conversions = fields.iter().map(|(field, result)| {
+        quote!(
+            let #field = protobuf_gen::error::if_set(#field, |#field| #result)
+                .map_err(|e| protobuf_gen::Error::new_try_from_error(stringify!(#field), e))?;
+        )
+    });
+    let fields: Vec<_> = fields.iter().map(|(field, _)| field).collect();
+    let defaults: Vec<Ident> =
+        fields.iter().map(|field| syn::parse_str(&format!("default_{}", field)).unwrap()).collect();
+    quote! {
+        #(#conversions)*
+        let (#(#fields,)*) = match (#(#fields,)*) {
+            (#(Some(#fields),)*) => (#(#fields,)*),
+            (#(#fields,)*) => {
+                let Self { #(#fields: #defaults,)* .. } = <Self as Default>::default();
+                (#(#fields.unwrap_or(#defaults),)*)
+            }
+        };
+    }
+}
+
+/// Returns the function converting a value of `ty`, which is `to_proxy`/`from_proxy` if `ty` has
+/// the inherent methods only, and `TryInto` otherwise.
+fn value_conversion(inherent: bool, ty: &Type, conversion: Conversion) -> TokenStream {
+    match (inherent, conversion) {
+        (false, _) => quote!(::std::convert::TryInto::try_into),
+        (true, Conversion::IntoProxy) => quote!(<#ty>::to_proxy),
+        (true, Conversion::FromProxy) | (true, Conversion::FromProxyOrDefault) => {
+            quote!(<#ty>::from_proxy)
+        }
+    }
+}
+
+// The type of the values of a collection or an `Option`, e.g. `V` of `HashMap<K, V>`.
+fn value_type(type_path: &TypePath) -> Option<&Type> {
+    match &type_path.path.segments.last()?.arguments {
+        PathArguments::AngleBracketed(arguments) => {
+            arguments.args.iter().rev().find_map(|argument| match argument {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::index::ProtoType;
+
+    // A generator with `City`, `AreaCode` and `Session` with the inherent conversions only in the
+    // index as `Config::generate` writes it.
+    fn generator(doc_hidden: Option<bool>) -> ConversionGenerator {
+        let mut index = TypeIndex::default();
+        for (name, inherent) in &[("City", false), ("AreaCode", false), ("Session", true)] {
+            let ty = ProtoType {
+                package: "yellow_book".to_string(),
+                path: "yellow_book.proto".into(),
+                inherent: *inherent,
+            };
+            index.insert(name, ty);
+        }
+        ConversionGenerator {
+            token_stream: TokenStream::default(),
+            proxy_mod: syn::parse_str("crate::proxy").unwrap(),
+            doc_hidden,
+            inherent: false,
+            trait_impls: true,
+            default: false,
+            settings: Settings::default(),
+            index: Some(index),
+        }
+    }
+
+    // Generates the conversions of `source`.
+    fn generate(source: &str, doc_hidden: Option<bool>) -> String {
+        let mut generator = generator(doc_hidden);
+        crate::extract::extract_item(&mut generator, &syn::parse_str(source).unwrap()).unwrap();
+        generator.token_stream.to_string()
+    }
+
+    const CITY: &str = "pub struct City { pub name: String, pub area_code: AreaCode }";
+    const AREA_CODE: &str = "pub enum AreaCode { Seoul = 2, Busan = 51 }";
+
+    #[test]
+    fn docs_name_proto_message_and_file() {
+        let city = generate(CITY, Some(false));
+        assert!(city.contains(
+            "# [doc = \"Converts into the protobuf message `yellow_book.City` of \
+             `yellow_book.proto` (`crate::proxy::City`).\"]"
+        ));
+
+        let area_code = generate(AREA_CODE, Some(false));
+        assert!(area_code.contains(
+            "# [doc = \"Encodes and decodes as the protobuf `yellow_book.AreaCode` of \
+             `yellow_book.proto` (`crate::proxy::AreaCode`).\"]"
+        ));
+    }
+
+    #[test]
+    fn default_is_built_once_per_conversion() {
+        let mut generator = generator(None);
+        generator.default = true;
+        let item = syn::parse_str(
+            "pub struct Preferences { pub language: String, pub home: City, pub work: City }",
+        )
+        .unwrap();
+        crate::extract::extract_item(&mut generator, &item).unwrap();
+
+        let preferences = generator.token_stream.to_string();
+        let conversions = preferences.matches("let (language , home , work ,) = match").count();
+        assert!(conversions > 0, "{}", preferences);
+        assert_eq!(preferences.matches("< Self as Default > :: default ()").count(), conversions);
+    }
+
+    #[test]
+    fn docs_name_proxy_only_of_unknown_type() {
+        let street = generate("pub struct Street { pub name: String }", Some(false));
+        assert!(street.contains(
+            "# [doc = \"Converts into the protobuf message `Street` (`crate::proxy::Street`).\"]"
+        ));
+    }
+
+    #[test]
+    fn dispatch_calls_method_of_each_variant() {
+        let mut generator = generator(None);
+        let item = syn::parse_str(
+            "pub enum Request { Register(City), Lookup { name: String, exact: bool }, Count }",
+        )
+        .unwrap();
+        let response = syn::parse_str("\"Response\"").unwrap();
+        generator.add_dispatch(&item, &response).unwrap();
+
+        let dispatch = generator.token_stream.to_string();
+        assert!(dispatch.contains("pub trait RequestHandler"));
+        assert!(dispatch.contains("fn register (& mut self , request : City) -> Response ;"));
+        assert!(dispatch
+            .contains("fn lookup (& mut self , name : String , exact : bool) -> Response ;"));
+        assert!(dispatch.contains("fn count (& mut self) -> Response ;"));
+        assert!(dispatch
+            .contains("Request :: Lookup { name , exact } => handler . lookup (name , exact) ,"));
+
+        let item = syn::parse_str("pub struct Request { pub name: String }").unwrap();
+        let error = generator.add_dispatch(&item, &response).unwrap_err();
+        assert!(error.to_string().starts_with("protobuf_gen::invalid_attribute: "));
+    }
+
+    #[test]
+    fn ffi_needs_package_from_index() {
+        let city = Ident::new("City", proc_macro2::Span::call_site());
+        let mut generator = generator(None);
+        generator.add_ffi(&city).unwrap();
+        assert!(generator.token_stream.to_string().contains("fn yellow_book_city_decode"));
+
+        let street = Ident::new("Street", proc_macro2::Span::call_site());
+        let error = generator.add_ffi(&street).unwrap_err().to_string();
+        assert!(error.contains("`Street` is not in the schemas"), "{}", error);
+
+        generator.index = None;
+        let error = generator.add_ffi(&city).unwrap_err().to_string();
+        assert!(error.contains(INDEX_FILE), "{}", error);
+    }
+
+    #[test]
+    fn inherent_types_from_index_or_attribute() {
+        let login = generate(
+            "pub struct Login { pub session: Session, pub previous: Vec<Session>, \
+             #[protobuf_gen(inherent)] pub tier: Tier, pub city: City }",
+            None,
+        );
+        assert!(login.contains("< Session > :: to_proxy (session)"), "{}", login);
+        assert!(login.contains("map (| x | < Session > :: to_proxy (x)"), "{}", login);
+        assert!(login.contains("< Tier > :: to_proxy (tier)"), "{}", login);
+        assert!(!login.contains("< City > :: to_proxy"), "{}", login);
+    }
+
+    #[test]
+    fn doc_hidden_hides_generated_items() {
+        for source in &[CITY, AREA_CODE] {
+            let hidden = generate(source, Some(true));
+            assert!(hidden.contains("# [doc (hidden)]"));
+            assert!(!hidden.contains("# [doc ="));
+
+            let undocumented = generate(source, None);
+            assert!(!undocumented.contains("# [doc"));
+        }
     }
 }
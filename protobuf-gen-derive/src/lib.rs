@@ -2,58 +2,170 @@
 
 use protobuf_gen_extract as extract;
 
+mod anonymize;
 mod convert;
+mod proto_default;
+
+use std::path::PathBuf;
 
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
-use syn::{Fields, Item, ItemEnum, ItemStruct, TypePath};
+use syn::{Item, ItemEnum, ItemStruct, Lit, TypePath};
 
 use convert::ConversionGenerator;
-use extract::Extract;
+use extract::diagnostic::{self, Diagnostic};
+use extract::index::TypeIndex;
+use extract::settings::Settings;
 
 #[proc_macro_derive(ProtobufGen, attributes(protobuf_gen))]
 pub fn derive_protobuf_gen(input: TokenStream) -> TokenStream {
     let item = syn::parse_macro_input!(input as Item);
 
-    match &item {
+    derive(&item).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// Generates the conversions `#[derive(ProtobufGen)]` would for a type which cannot be annotated,
+/// from an explicit list of its fields. See `protobuf_gen_extract::Conversions` for the syntax.
+#[proc_macro]
+pub fn conversions(input: TokenStream) -> TokenStream {
+    let conversions = syn::parse_macro_input!(input as extract::Conversions);
+
+    derive(&conversions.to_item()).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+fn derive(item: &Item) -> syn::Result<TokenStream2> {
+    diagnostic::check_item(item)?;
+
+    match item {
         Item::Struct(ItemStruct { attrs, .. }) | Item::Enum(ItemEnum { attrs, .. }) => {
             if let Some(proxy_mod) =
-                syn_util::get_attribute_value::<String>(attrs, &["protobuf_gen", "proxy_mod"])
+                syn_util::get_attribute_value::<Lit>(attrs, &["protobuf_gen", "proxy_mod"])
             {
-                return generate_conversion_apis(
-                    &item,
-                    syn::parse_str(&proxy_mod).unwrap_or_else(|_| {
-                        panic!("invalid proxy_mod attribyte: \"{}\"", proxy_mod)
-                    }),
-                )
-                .into();
+                let proxy_mod = match &proxy_mod {
+                    Lit::Str(lit_str) => lit_str.parse().map_err(|_| {
+                        Diagnostic::InvalidProxyMod.error(
+                            lit_str.span(),
+                            format!("invalid proxy_mod attribute: \"{}\"", lit_str.value()),
+                        )
+                    })?,
+                    _ => unreachable!(),
+                };
+                return generate_conversion_apis(item, proxy_mod);
             }
         }
         _ => {}
     }
-    TokenStream2::default().into()
+    Ok(TokenStream2::default())
 }
 
-fn generate_conversion_apis(item: &Item, proxy_mod: TypePath) -> TokenStream2 {
-    let mut builder = ConversionGenerator { token_stream: TokenStream2::default(), proxy_mod };
+// Returns the settings of the crate being built, with the path of its `protobuf-gen.conf` if it
+// has one. They are read for every type rather than cached, since the compiler process may
+// outlive an edit of the file.
+fn crate_settings() -> syn::Result<(Option<PathBuf>, Settings)> {
+    let path = match Settings::manifest_path() {
+        Some(path) => path,
+        None => return Ok((None, Settings::default())),
+    };
+    let settings =
+        Settings::load(&path).map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), e))?;
+    Ok((Some(path), settings))
+}
 
-    match item {
-        Item::Struct(item_struct) => {
-            protobuf_gen_extract::extract_message(&mut builder, item_struct);
-        }
-        Item::Enum(item_enum) => {
-            if item_enum.variants.iter().all(|v| matches!(v.fields, Fields::Unit)) {
-                builder.extract_enumerator(item_enum);
-            } else {
-                builder.extract_one_of(item_enum);
-
-                for variant in &item_enum.variants {
-                    protobuf_gen_extract::extract_nested_message(&mut builder, item_enum, variant);
-                }
+fn generate_conversion_apis(item: &Item, proxy_mod: TypePath) -> syn::Result<TokenStream2> {
+    let doc_hidden = match item {
+        Item::Struct(ItemStruct { attrs, .. }) | Item::Enum(ItemEnum { attrs, .. }) => {
+            syn_util::get_attribute_value::<bool>(attrs, &["protobuf_gen", "doc_hidden"])
+        }
+        _ => None,
+    };
+    let (inherent, keep_trait_impls) = match item {
+        Item::Struct(ItemStruct { ident, attrs, .. })
+        | Item::Enum(ItemEnum { ident, attrs, .. }) => {
+            let inherent = syn_util::contains_attribute(attrs, &["protobuf_gen", "inherent"]);
+            let keep_trait_impls =
+                syn_util::contains_attribute(attrs, &["protobuf_gen", "keep_trait_impls"]);
+            if keep_trait_impls && !inherent {
+                return Err(Diagnostic::InvalidAttribute
+                    .error(ident.span(), "`keep_trait_impls` requires `inherent`"));
+            }
+            (inherent, keep_trait_impls)
+        }
+        _ => (false, false),
+    };
+    let (settings_path, settings) = crate_settings()?;
+    let ffi = match item {
+        Item::Struct(ItemStruct { attrs, .. }) | Item::Enum(ItemEnum { attrs, .. }) => {
+            syn_util::contains_attribute(attrs, &["protobuf_gen", "ffi"])
+        }
+        _ => false,
+    };
+    // tracked so that the docs, C functions and conversions of fields follow the schemas
+    let index = TypeIndex::from_out_dir();
+    let index_path = index.as_ref().map(|(path, _)| path.display().to_string());
+    let mut builder = ConversionGenerator {
+        token_stream: TokenStream2::default(),
+        proxy_mod,
+        doc_hidden,
+        inherent,
+        trait_impls: !inherent || keep_trait_impls,
+        default: match item {
+            Item::Struct(ItemStruct { attrs, .. }) | Item::Enum(ItemEnum { attrs, .. }) => {
+                syn_util::contains_attribute(attrs, &["protobuf_gen", "default"])
             }
+            _ => false,
+        },
+        settings,
+        index: index.map(|(_, index)| index),
+    };
+    // tracked so that the conversions follow the settings
+    let settings_path = settings_path.map(|path| path.display().to_string());
+    for path in index_path.iter().chain(&settings_path) {
+        builder.token_stream.extend(quote::quote! {
+            const _: &[u8] = include_bytes!(#path);
+        });
+    }
+
+    protobuf_gen_extract::extract_item(&mut builder, item)?;
+
+    if let Item::Struct(ItemStruct { ident, attrs, .. })
+    | Item::Enum(ItemEnum { ident, attrs, .. }) = item
+    {
+        if let Some(version) =
+            syn_util::get_attribute_value::<u64>(attrs, &["protobuf_gen", "schema_version"])
+        {
+            builder.add_schema_version(ident, version)?;
+        }
+
+        if syn_util::contains_attribute(attrs, &["protobuf_gen", "wire_struct"]) {
+            builder.add_wire_struct(item)?;
+        }
+
+        if let Some(type_name) =
+            syn_util::get_attribute_value::<String>(attrs, &["protobuf_gen", "status"])
+        {
+            builder.add_status(item, &type_name)?;
+        }
+
+        if let Some(Lit::Str(response)) =
+            syn_util::get_attribute_value::<Lit>(attrs, &["protobuf_gen", "dispatch"])
+        {
+            builder.add_dispatch(item, &response)?;
+        }
+
+        if ffi {
+            builder.add_ffi(ident)?;
+        }
+
+        if syn_util::contains_attribute(attrs, &["protobuf_gen", "proto_default"]) {
+            builder
+                .token_stream
+                .extend(proto_default::generate_proto_default(item, &builder.proxy_mod));
+        }
+
+        if syn_util::contains_attribute(attrs, &["protobuf_gen", "anonymize"]) {
+            builder.token_stream.extend(anonymize::generate_anonymize(item)?);
         }
-        _ => unreachable!(),
     }
 
-    builder.token_stream
+    Ok(builder.token_stream)
 }
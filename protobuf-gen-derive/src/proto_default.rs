@@ -0,0 +1,71 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Field, Fields, Item, ItemEnum, ItemStruct, TypePath, Visibility};
+
+use crate::extract::OptionalEnum;
+
+pub(crate) fn generate_proto_default(item: &Item, proxy: &TypePath) -> TokenStream {
+    let (ident, body) = match item {
+        Item::Struct(item_struct) => (&item_struct.ident, generate_struct(item_struct, proxy)),
+        Item::Enum(item_enum) => (&item_enum.ident, generate_enum(item_enum)),
+        _ => unreachable!(),
+    };
+
+    quote! {
+        impl protobuf_gen::ProtoDefault for #ident {
+            fn is_proto_default(&self) -> bool {
+                #body
+            }
+        }
+    }
+}
+
+fn generate_struct(item_struct: &ItemStruct, proxy: &TypePath) -> TokenStream {
+    let ident = &item_struct.ident;
+    let checks = item_struct
+        .fields
+        .iter()
+        .filter(|field| {
+            matches!(field.vis, Visibility::Public(_))
+                || syn_util::contains_attribute(&field.attrs, &["protobuf_gen", "expose"])
+        })
+        .map(|field| check_field(field, quote!(#proxy::#ident)));
+
+    quote!(true #(&& #checks)*)
+}
+
+fn check_field(field: &Field, proxy_message: TokenStream) -> TokenStream {
+    let field_ident = field.ident.as_ref().unwrap();
+    if syn_util::get_attribute_value::<String>(&field.attrs, &["protobuf_gen", "encrypt"]).is_some()
+    {
+        quote!(false)
+    } else if syn_util::get_attribute_value::<String>(&field.attrs, &["protobuf_gen", "substitute"])
+        .is_some()
+    {
+        quote! {
+            protobuf_gen::proto_default::substitute(
+                ::std::clone::Clone::clone(&self.#field_ident),
+                |message: #proxy_message| message.#field_ident,
+            )
+        }
+    } else {
+        quote!(protobuf_gen::ProtoDefault::is_proto_default(&self.#field_ident))
+    }
+}
+
+fn generate_enum(item_enum: &ItemEnum) -> TokenStream {
+    let ident = &item_enum.ident;
+    if let Some(optional_enum) = OptionalEnum::new(item_enum) {
+        let none = &optional_enum.none.ident;
+        return quote!(matches!(self, #ident::#none));
+    }
+
+    let is_unit = item_enum.variants.iter().all(|v| matches!(v.fields, Fields::Unit));
+    match item_enum.variants.first() {
+        Some(first) if is_unit => {
+            let first = &first.ident;
+            quote!(matches!(self, #ident::#first))
+        }
+        _ => quote!(false),
+    }
+}
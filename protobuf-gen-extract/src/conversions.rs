@@ -0,0 +1,141 @@
+//! The input of the `conversions!` macro, for types which cannot be annotated with
+//! `#[derive(ProtobufGen)]`, e.g. types generated by other macros or by `bindgen`:
+//!
+//! ```ignore
+//! protobuf_gen::conversions!(Point => proxy_mod = crate::proxy, fields(x: i32, y: i32));
+//! ```
+//!
+//! Options other than `proxy_mod`, e.g. `schema_version = 2` or `inherent`, may follow
+//! `proxy_mod` as in `#[protobuf_gen(..)]`. `fields(..)` lists every field of the `struct` with
+//! its type and field attributes, in the order of their field numbers.
+
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_quote, Field, Ident, Item, ItemMacro, ItemUse, LitStr, NestedMeta, Token, TypePath,
+    UseGroup, UseName, UsePath, UseRename, UseTree, Visibility,
+};
+
+pub struct Conversions {
+    pub ident: Ident,
+    pub proxy_mod: TypePath,
+    pub options: Vec<NestedMeta>,
+    pub fields: Punctuated<Field, Token![,]>,
+}
+
+mod kw {
+    syn::custom_keyword!(proxy_mod);
+    syn::custom_keyword!(fields);
+}
+
+impl Parse for Conversions {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        input.parse::<kw::proxy_mod>()?;
+        input.parse::<Token![=]>()?;
+        let proxy_mod = input.parse()?;
+        input.parse::<Token![,]>()?;
+
+        let mut options = Vec::new();
+        while !input.peek(kw::fields) {
+            options.push(input.parse()?);
+            input.parse::<Token![,]>()?;
+        }
+
+        input.parse::<kw::fields>()?;
+        let content;
+        syn::parenthesized!(content in input);
+        let fields = content.parse_terminated(Field::parse_named)?;
+        input.parse::<Option<Token![,]>>()?;
+
+        Ok(Self { ident, proxy_mod, options, fields })
+    }
+}
+
+impl Conversions {
+    /// Returns the `struct` which `#[derive(ProtobufGen)]` would see if the type were annotated.
+    ///
+    /// All the listed fields are public, so that they appear in the schema.
+    pub fn to_item(&self) -> Item {
+        let Self { ident, proxy_mod, options, .. } = self;
+        let proxy_mod =
+            LitStr::new(&proxy_mod.to_token_stream().to_string().replace(' ', ""), ident.span());
+        let fields = self.fields.iter().map(|field| Field {
+            vis: Visibility::Public(syn::VisPublic { pub_token: Default::default() }),
+            ..field.clone()
+        });
+
+        parse_quote! {
+            #[protobuf_gen(proxy_mod = #proxy_mod, #(#options),*)]
+            struct #ident {
+                #(#fields),*
+            }
+        }
+    }
+
+    /// Returns the `protobuf_gen::conversions!` invocation in `item`, if any. Invocations which do
+    /// not parse are skipped, since the macro itself reports them.
+    pub fn from_item(item: &Item) -> Option<Self> {
+        Self::from_item_in(item, &[])
+    }
+
+    /// Returns the `conversions!` invocation in `item` as `from_item` does, and also the bare or
+    /// renamed invocations which `use` declarations in `items` import from `protobuf_gen`.
+    pub fn from_item_in(item: &Item, items: &[Item]) -> Option<Self> {
+        let mac = match item {
+            Item::Macro(ItemMacro { mac, .. }) => mac,
+            _ => return None,
+        };
+        let segments: Vec<_> = mac.path.segments.iter().map(|segment| &segment.ident).collect();
+        let is_conversions = match segments[..] {
+            [krate, name] => krate == "protobuf_gen" && name == "conversions",
+            [name] => imported_names(items).iter().any(|imported| name == imported),
+            _ => false,
+        };
+        if is_conversions {
+            mac.parse_body().ok()
+        } else {
+            None
+        }
+    }
+}
+
+// Returns the names under which `use` declarations in `items` import `protobuf_gen::conversions`.
+fn imported_names(items: &[Item]) -> Vec<Ident> {
+    fn collect(tree: &UseTree, in_protobuf_gen: bool, names: &mut Vec<Ident>) {
+        match tree {
+            UseTree::Path(UsePath { ident, tree, .. }) if !in_protobuf_gen => {
+                if ident == "protobuf_gen" {
+                    collect(tree, true, names);
+                }
+            }
+            UseTree::Name(UseName { ident }) if in_protobuf_gen && ident == "conversions" => {
+                names.push(ident.clone());
+            }
+            UseTree::Rename(UseRename { ident, rename, .. })
+                if in_protobuf_gen && ident == "conversions" =>
+            {
+                names.push(rename.clone());
+            }
+            UseTree::Glob(glob) if in_protobuf_gen => {
+                names.push(Ident::new("conversions", glob.star_token.span));
+            }
+            UseTree::Group(UseGroup { items, .. }) => {
+                for tree in items {
+                    collect(tree, in_protobuf_gen, names);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut names = Vec::new();
+    for item in items {
+        if let Item::Use(ItemUse { tree, .. }) = item {
+            collect(tree, false, &mut names);
+        }
+    }
+    names
+}
@@ -0,0 +1,342 @@
+//! Diagnostics reported by `#[derive(ProtobufGen)]`.
+//!
+//! Every error message starts with a stable prefix followed by `: `, so compile-fail tests
+//! (e.g. with `trybuild`) can assert on the kind of misuse without depending on the wording
+//! that follows it:
+//!
+//! | prefix                                | reported when                                       |
+//! |---------------------------------------|-----------------------------------------------------|
+//! | `protobuf_gen::unknown_attribute`     | a `#[protobuf_gen(..)]` key is not recognized       |
+//! | `protobuf_gen::invalid_attribute`     | a `#[protobuf_gen(..)]` key has a value of bad type |
+//! | `protobuf_gen::invalid_proxy_mod`     | `proxy_mod` is not a valid path                     |
+//! | `protobuf_gen::unsupported_item`      | the type cannot be converted to a message or enum   |
+//! | `protobuf_gen::unsupported_variant`   | an enum variant cannot be converted to a message    |
+
+use std::ffi::OsStr;
+use std::fmt::Display;
+use std::path::{Component, Path};
+
+use proc_macro2::Span;
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Fields, GenericArgument, Item, Lit, LitStr, Meta, NestedMeta, PathArguments, Type,
+};
+
+use crate::status::Code;
+use crate::OptionalEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagnostic {
+    UnknownAttribute,
+    InvalidAttribute,
+    InvalidProxyMod,
+    UnsupportedItem,
+    UnsupportedVariant,
+}
+
+impl Diagnostic {
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Diagnostic::UnknownAttribute => "protobuf_gen::unknown_attribute",
+            Diagnostic::InvalidAttribute => "protobuf_gen::invalid_attribute",
+            Diagnostic::InvalidProxyMod => "protobuf_gen::invalid_proxy_mod",
+            Diagnostic::UnsupportedItem => "protobuf_gen::unsupported_item",
+            Diagnostic::UnsupportedVariant => "protobuf_gen::unsupported_variant",
+        }
+    }
+
+    pub fn error<T: Display>(self, span: Span, message: T) -> syn::Error {
+        syn::Error::new(span, format!("{}: {}", self.prefix(), message))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Flag,
+    Bool,
+    Str,
+    Int,
+}
+
+const CONTAINER_ATTRIBUTES: &[(&str, Value)] = &[
+    ("proxy_mod", Value::Str),
+    ("schema_version", Value::Int),
+    ("doc_hidden", Value::Bool),
+    ("anonymize", Value::Flag),
+    ("unique_by", Value::Str),
+    ("ffi", Value::Flag),
+    ("inherent", Value::Flag),
+    ("keep_trait_impls", Value::Flag),
+    ("wire_struct", Value::Flag),
+    ("file", Value::Str),
+    ("optional", Value::Flag),
+    ("default", Value::Flag),
+    ("status", Value::Str),
+    ("proto_default", Value::Flag),
+    ("dispatch", Value::Str),
+];
+
+const FIELD_ATTRIBUTES: &[(&str, Value)] = &[
+    ("expose", Value::Flag),
+    ("substitute", Value::Str),
+    ("encrypt", Value::Str),
+    ("sensitive", Value::Flag),
+    ("inherent", Value::Flag),
+];
+
+const VARIANT_ATTRIBUTES: &[(&str, Value)] = &[("code", Value::Str)];
+
+fn check_attributes(attrs: &[Attribute], allowed: &[(&str, Value)]) -> syn::Result<()> {
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("protobuf_gen"))
+    {
+        let nested = match attr.parse_meta()? {
+            Meta::List(meta_list) => meta_list.nested,
+            meta => {
+                return Err(Diagnostic::InvalidAttribute
+                    .error(meta.span(), "expected `#[protobuf_gen(..)]`"))
+            }
+        };
+
+        for nested_meta in nested {
+            let meta = match nested_meta {
+                NestedMeta::Meta(meta) => meta,
+                NestedMeta::Lit(lit) => {
+                    return Err(Diagnostic::InvalidAttribute.error(lit.span(), "expected a key"))
+                }
+            };
+
+            let key = meta
+                .path()
+                .get_ident()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            let expected = allowed
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| *value)
+                .ok_or_else(|| {
+                    Diagnostic::UnknownAttribute
+                        .error(meta.path().span(), format!("unknown attribute `{}`", key))
+                })?;
+
+            let found = match &meta {
+                Meta::Path(_) => Some(Value::Flag),
+                Meta::NameValue(name_value) => match name_value.lit {
+                    Lit::Bool(_) => Some(Value::Bool),
+                    Lit::Str(_) => Some(Value::Str),
+                    Lit::Int(_) => Some(Value::Int),
+                    _ => None,
+                },
+                Meta::List(_) => None,
+            };
+            if found != Some(expected) {
+                let expected = match expected {
+                    Value::Flag => format!("`{}`", key),
+                    Value::Bool => format!("`{} = <bool>`", key),
+                    Value::Str => format!("`{} = \"..\"`", key),
+                    Value::Int => format!("`{} = <integer>`", key),
+                };
+                return Err(Diagnostic::InvalidAttribute
+                    .error(meta.span(), format!("expected {}", expected)));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_proxy_mod(attrs: &[Attribute]) -> syn::Result<()> {
+    if let Some(Lit::Str(lit_str)) =
+        syn_util::get_attribute_value::<Lit>(attrs, &["protobuf_gen", "proxy_mod"])
+    {
+        lit_str.parse::<syn::TypePath>().map_err(|_| {
+            Diagnostic::InvalidProxyMod.error(
+                lit_str.span(),
+                format!("invalid proxy_mod attribute: \"{}\"", lit_str.value()),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+// Only `String` and `Vec<u8>` can be encrypted, since the ciphertext is `bytes` in which empty
+// means unset.
+fn check_encrypt(field: &syn::Field) -> syn::Result<()> {
+    if syn_util::get_attribute_value::<String>(&field.attrs, &["protobuf_gen", "encrypt"]).is_none()
+    {
+        return Ok(());
+    }
+    let is_u8 = |argument: &GenericArgument| matches!(argument, GenericArgument::Type(Type::Path(type_path)) if type_path.path.is_ident("u8"));
+    let supported = match &field.ty {
+        Type::Path(type_path) if type_path.qself.is_none() => {
+            match type_path.path.segments.last() {
+                Some(segment) if segment.ident == "String" => segment.arguments.is_empty(),
+                Some(segment) if segment.ident == "Vec" => match &segment.arguments {
+                    PathArguments::AngleBracketed(arguments) => {
+                        arguments.args.len() == 1 && is_u8(&arguments.args[0])
+                    }
+                    _ => false,
+                },
+                _ => false,
+            }
+        }
+        _ => false,
+    };
+    if supported {
+        Ok(())
+    } else {
+        Err(Diagnostic::InvalidAttribute.error(
+            field.ty.span(),
+            "`encrypt` is only supported for `String` and `Vec<u8>`",
+        ))
+    }
+}
+
+/// Checks that `#[protobuf_gen(file = "..")]` in `attrs`, if any, is a relative path of a
+/// `.proto` file inside the target directory.
+pub fn check_file(attrs: &[Attribute]) -> syn::Result<()> {
+    let lit_str = match syn_util::get_attribute_value::<Lit>(attrs, &["protobuf_gen", "file"]) {
+        Some(Lit::Str(lit_str)) => lit_str,
+        _ => return Ok(()),
+    };
+    let value = lit_str.value();
+    let path = Path::new(&value);
+    let reason = if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        "must be a relative path without `..`"
+    } else if path.extension() != Some(OsStr::new("proto")) {
+        "must have the extension `.proto`"
+    } else {
+        return Ok(());
+    };
+    Err(Diagnostic::InvalidAttribute
+        .error(lit_str.span(), format!("file \"{}\" {}", value, reason)))
+}
+
+fn check_code(code: &LitStr, has_status: bool) -> syn::Result<()> {
+    if !has_status {
+        return Err(Diagnostic::InvalidAttribute
+            .error(code.span(), "`code` requires `status` on the 'enum'"));
+    }
+    match Code::from_name(&code.value()) {
+        Some(Code::Ok) => {
+            Err(Diagnostic::InvalidAttribute
+                .error(code.span(), "an error cannot have the code \"OK\""))
+        }
+        Some(_) => Ok(()),
+        None => Err(Diagnostic::InvalidAttribute.error(
+            code.span(),
+            format!("unknown google.rpc.Code: \"{}\"", code.value()),
+        )),
+    }
+}
+
+/// Checks that `item` is supported by `#[derive(ProtobufGen)]` and that all of its
+/// `#[protobuf_gen(..)]` attributes are well-formed.
+pub fn check_item(item: &Item) -> syn::Result<()> {
+    match item {
+        Item::Struct(item_struct) => {
+            check_attributes(&item_struct.attrs, CONTAINER_ATTRIBUTES)?;
+            check_proxy_mod(&item_struct.attrs)?;
+            check_file(&item_struct.attrs)?;
+            if syn_util::contains_attribute(&item_struct.attrs, &["protobuf_gen", "optional"]) {
+                return Err(Diagnostic::InvalidAttribute.error(
+                    item_struct.ident.span(),
+                    "`optional` is only supported for 'enum'",
+                ));
+            }
+
+            let fields_named = match &item_struct.fields {
+                Fields::Named(fields_named) => fields_named,
+                _ => {
+                    return Err(Diagnostic::UnsupportedItem.error(
+                        item_struct.ident.span(),
+                        "only 'struct' with named fields can be converted to 'message'",
+                    ))
+                }
+            };
+            for field in &fields_named.named {
+                check_attributes(&field.attrs, FIELD_ATTRIBUTES)?;
+                check_encrypt(field)?;
+            }
+
+            let is_exposed = |field: &syn::Field| {
+                matches!(field.vis, syn::Visibility::Public(_))
+                    || syn_util::contains_attribute(&field.attrs, &["protobuf_gen", "expose"])
+            };
+            if !fields_named.named.iter().any(is_exposed) {
+                return Err(Diagnostic::UnsupportedItem.error(
+                    item_struct.ident.span(),
+                    "'message' needs at least one public or `expose`d field",
+                ));
+            }
+        }
+        Item::Enum(item_enum) => {
+            check_attributes(&item_enum.attrs, CONTAINER_ATTRIBUTES)?;
+            check_proxy_mod(&item_enum.attrs)?;
+            check_file(&item_enum.attrs)?;
+            let has_status = syn_util::get_attribute_value::<String>(
+                &item_enum.attrs,
+                &["protobuf_gen", "status"],
+            )
+            .is_some();
+            for variant in &item_enum.variants {
+                check_attributes(&variant.attrs, VARIANT_ATTRIBUTES)?;
+                if let Some(Lit::Str(code)) =
+                    syn_util::get_attribute_value::<Lit>(&variant.attrs, &["protobuf_gen", "code"])
+                {
+                    check_code(&code, has_status)?;
+                }
+                match &variant.fields {
+                    Fields::Named(fields_named) => {
+                        for field in &fields_named.named {
+                            check_attributes(&field.attrs, FIELD_ATTRIBUTES)?;
+                            check_encrypt(field)?;
+                        }
+                    }
+                    Fields::Unit => {}
+                    Fields::Unnamed(fields_unnamed) if fields_unnamed.unnamed.len() == 1 => {
+                        let field = &fields_unnamed.unnamed[0];
+                        check_attributes(&field.attrs, FIELD_ATTRIBUTES)?;
+                        if syn_util::get_attribute_value::<String>(
+                            &field.attrs,
+                            &["protobuf_gen", "encrypt"],
+                        )
+                        .is_some()
+                        {
+                            return Err(Diagnostic::InvalidAttribute.error(
+                                field.span(),
+                                "`encrypt` is only supported for named fields",
+                            ));
+                        }
+                    }
+                    Fields::Unnamed(_) => {
+                        return Err(Diagnostic::UnsupportedVariant.error(
+                            variant.ident.span(),
+                            "only unit, 'struct' with named fields and a single unnamed field can be converted to 'oneof' field",
+                        ))
+                    }
+                }
+            }
+
+            if syn_util::contains_attribute(&item_enum.attrs, &["protobuf_gen", "optional"])
+                && OptionalEnum::new(item_enum).is_none()
+            {
+                return Err(Diagnostic::UnsupportedItem.error(
+                    item_enum.ident.span(),
+                    "`optional` requires one unit variant and one variant with a single unnamed field",
+                ));
+            }
+        }
+        _ => {
+            return Err(Diagnostic::UnsupportedItem.error(
+                Span::call_site(),
+                "only 'struct' and 'enum' can derive `ProtobufGen`",
+            ))
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+//! The package and `.proto` file of every generated type, written by `Config::generate` to the
+//! `OUT_DIR` of the build script running it, so that `#[derive(ProtobufGen)]` in the same crate
+//! names the protobuf types in the docs it generates and converts fields of such types.
+//!
+//! Each line is `<name> <package> <kind> <path>`, e.g. `City yellow_book - yellow_book.proto`,
+//! where the kind is `inherent` for a type with the inherent conversions only. Types are
+//! keyed by their fully-qualified names, so types of the same name in different packages are all
+//! kept, but the derive only knows the name of a type and finds no package for such types.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use heck::SnakeCase;
+
+pub const INDEX_FILE: &str = "protobuf-gen.index";
+
+/// Where a type is in the schemas.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtoType {
+    pub package: String,
+    /// The path of the `.proto` file relative to the target directory.
+    pub path: PathBuf,
+    /// Whether the type has `#[protobuf_gen(inherent)]` without `keep_trait_impls`, so fields of
+    /// the type are converted with `to_proxy` and `from_proxy`.
+    pub inherent: bool,
+}
+
+impl ProtoType {
+    /// Returns the fully-qualified protobuf name of the type `name`, e.g. `yellow_book.City`.
+    pub fn full_name(&self, name: &str) -> String {
+        if self.package.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.package, name)
+        }
+    }
+
+    /// Returns the prefix of the C functions of the type `name`, e.g. `yellow_book_city`.
+    pub fn ffi_prefix(&self, name: &str) -> String {
+        ffi_prefix(&self.package, name)
+    }
+}
+
+/// Returns the name of the C struct of the type `name` in `package`, e.g. `yellow_book_City`,
+/// which is qualified by the package as the functions are.
+pub fn ffi_type(package: &str, name: &str) -> String {
+    if package.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}_{}", package.replace('.', "_"), name)
+    }
+}
+
+/// Returns the prefix of the C functions of the type `name` in `package`, which is qualified by
+/// the package so that the symbols of types of the same name do not collide when linked together.
+pub fn ffi_prefix(package: &str, name: &str) -> String {
+    if package.is_empty() {
+        name.to_snake_case()
+    } else {
+        format!("{}_{}", package.replace('.', "_"), name.to_snake_case())
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TypeIndex {
+    /// The types by their fully-qualified names.
+    types: BTreeMap<String, ProtoType>,
+}
+
+impl TypeIndex {
+    /// Reads the index in `OUT_DIR`, returning its path as well, or `None` if there is none, e.g.
+    /// the crate has no build script generating schemas.
+    pub fn from_out_dir() -> Option<(PathBuf, Self)> {
+        let path = Path::new(&std::env::var_os("OUT_DIR")?).join(INDEX_FILE);
+        let s = fs::read_to_string(&path).ok()?;
+        Some((path, Self::parse(&s)))
+    }
+
+    /// Parses an index, skipping malformed lines.
+    pub fn parse(s: &str) -> Self {
+        let mut index = Self::default();
+        for line in s.lines() {
+            let mut columns = line.splitn(4, ' ');
+            if let (Some(name), Some(package), Some(kind), Some(path)) = (
+                columns.next(),
+                columns.next(),
+                columns.next(),
+                columns.next(),
+            ) {
+                let ty = ProtoType {
+                    package: package.to_string(),
+                    path: PathBuf::from(path),
+                    inherent: kind == "inherent",
+                };
+                index.insert(name, ty);
+            }
+        }
+        index
+    }
+
+    pub fn insert(&mut self, name: &str, ty: ProtoType) {
+        self.types.insert(ty.full_name(name), ty);
+    }
+
+    /// Returns where the type `name` is, or `None` unless exactly one package has a type of the
+    /// name.
+    pub fn get(&self, name: &str) -> Option<&ProtoType> {
+        let mut types = self.find(name);
+        match (types.next(), types.next()) {
+            (Some(ty), None) => Some(ty),
+            _ => None,
+        }
+    }
+
+    /// Returns the types named `name` in all packages.
+    pub fn find<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a ProtoType> + 'a {
+        let name = name.to_string();
+        self.types
+            .iter()
+            .filter(move |(full_name, ty)| **full_name == ty.full_name(&name))
+            .map(|(_, ty)| ty)
+    }
+}
+
+impl fmt::Display for TypeIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (full_name, ty) in &self.types {
+            let name = full_name.rsplit('.').next().unwrap_or(full_name);
+            let kind = if ty.inherent { "inherent" } else { "-" };
+            writeln!(f, "{} {} {} {}", name, ty.package, kind, ty.path.display())?;
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,110 @@
+//! The extraction pass shared by `#[derive(ProtobufGen)]`, the schema generator of
+//! `protobuf-gen` and external code generators.
+//!
+//! An `Extract` visits every item deriving `ProtobufGen` or defined with `conversions!`. For each
+//! item, the callbacks are called in this order:
+//!
+//! 1. `extract_message_with_fields_named` for a `struct`, `extract_enumerator` for an `enum` of
+//!    unit variants, `extract_optional_enum` for a `#[protobuf_gen(optional)]` `enum`, or
+//!    `extract_one_of` for any other `enum`, followed by `extract_one_of_variant` and then the
+//!    nested message callback for each variant,
+//! 2. `extract_attributes` and `extract_doc` for the item, and then for each of its variants and
+//!    fields in declaration order,
+//! 3. `extract_field` for each field, followed by `extract_map_field` or
+//!    `extract_optional_field` for a field of such a type.
+//!
+//! Fields of a `struct` are only visited if they appear in the schema, i.e. they are public or
+//! `#[protobuf_gen(expose)]`.
+//!
+//! This is a public API: new callbacks are only added with a default implementation which does
+//! nothing, so an implementation only overrides the callbacks it needs.
+
+mod conversions;
+pub mod diagnostic;
+pub mod index;
+pub mod settings;
+pub mod status;
+
+use std::borrow::Cow;
+
+pub use conversions::Conversions;
+use diagnostic::Diagnostic;
+use syn::spanned::Spanned;
 use syn::{
-    Fields, FieldsNamed, File, Item, ItemEnum, ItemStruct, Meta, MetaList, NestedMeta, Path,
-    Variant,
+    Field, Fields, FieldsNamed, File, GenericArgument, Ident, Item, ItemEnum, ItemStruct, Lit,
+    Meta, MetaList, NestedMeta, Path, PathArguments, Type, Variant,
 };
 
+/// A `key` or `key = value` in `#[protobuf_gen(..)]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub key: String,
+    pub value: Option<Lit>,
+}
+
+/// Where an attribute or a doc comment is attached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Target<'a> {
+    pub item: &'a Ident,
+    pub variant: Option<&'a Ident>,
+    pub field: Option<&'a Ident>,
+}
+
+/// A variant of an `enum` converted to a `oneof` field.
+#[derive(Debug, Clone, Copy)]
+pub struct OneOfVariant<'a> {
+    pub variant: &'a Variant,
+    /// The field number of the variant in the `oneof`.
+    pub number: usize,
+    pub kind: OneOfVariantKind<'a>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum OneOfVariantKind<'a> {
+    /// An empty nested message.
+    Unit,
+    /// A nested message with the fields.
+    Message(&'a FieldsNamed),
+    /// A field of the type, without nesting.
+    Type(&'a Type),
+}
+
+/// An `enum` with `#[protobuf_gen(optional)]`, e.g. `enum Maybe { None, Some(T) }`, which is
+/// converted to an optional field of its `some` type wherever it is the type of a field, instead of
+/// a message with a `oneof`.
+#[derive(Debug, Clone, Copy)]
+pub struct OptionalEnum<'a> {
+    /// The unit variant, converted from an unset field.
+    pub none: &'a Variant,
+    /// The variant with a single unnamed field, converted from a set field.
+    pub some: &'a Variant,
+    pub ty: &'a Type,
+}
+
+impl<'a> OptionalEnum<'a> {
+    /// Returns the variants of `item_enum` if it has `#[protobuf_gen(optional)]` and exactly one
+    /// unit variant and one variant with a single unnamed field.
+    pub fn new(item_enum: &'a ItemEnum) -> Option<Self> {
+        if !syn_util::contains_attribute(&item_enum.attrs, &["protobuf_gen", "optional"])
+            || item_enum.variants.len() != 2
+        {
+            return None;
+        }
+
+        let none = item_enum
+            .variants
+            .iter()
+            .find(|v| matches!(v.fields, Fields::Unit))?;
+        let (some, ty) = item_enum.variants.iter().find_map(|v| match &v.fields {
+            Fields::Unnamed(fields_unnamed) if fields_unnamed.unnamed.len() == 1 => {
+                Some((v, &fields_unnamed.unnamed[0].ty))
+            }
+            _ => None,
+        })?;
+        Some(Self { none, some, ty })
+    }
+}
+
 pub trait Extract {
     fn extract_message_with_fields_named(&mut self, _: &ItemStruct, _: &FieldsNamed) {}
 
@@ -18,14 +120,237 @@ pub trait Extract {
 
     fn extract_one_of(&mut self, _: &ItemEnum) {}
 
+    fn extract_one_of_variant(&mut self, _: &ItemEnum, _: &OneOfVariant) {}
+
     fn extract_enumerator(&mut self, _: &ItemEnum) {}
+
+    fn extract_optional_enum(&mut self, _: &ItemEnum, _: &OptionalEnum) {}
+
+    /// Called with the `#[protobuf_gen(..)]` attributes of an item, a variant or a field, if any.
+    fn extract_attributes(&mut self, _: &Target, _: &[Attribute]) {}
+
+    /// Called with the doc comment of an item, a variant or a field, if any.
+    fn extract_doc(&mut self, _: &Target, _: &str) {}
+
+    /// Called for every field of a `struct` or of a variant with named fields which appears in
+    /// the schema.
+    fn extract_field(&mut self, _: &Target, _: &Field) {}
+
+    /// Called for a field of `HashMap`, `BTreeMap` or `IndexMap` with its key and value types.
+    fn extract_map_field(&mut self, _: &Target, _: &Field, _: &Type, _: &Type) {}
+
+    /// Called for a field of `Option` with its inner type.
+    fn extract_optional_field(&mut self, _: &Target, _: &Field, _: &Type) {}
+}
+
+/// Returns the `#[protobuf_gen(..)]` attributes in `attrs`.
+pub fn attributes(attrs: &[syn::Attribute]) -> Vec<Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("protobuf_gen"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(meta_list)) => Some(meta_list.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested_meta| match nested_meta {
+            NestedMeta::Meta(Meta::Path(path)) => Some(Attribute {
+                key: path_to_string(&path),
+                value: None,
+            }),
+            NestedMeta::Meta(Meta::NameValue(name_value)) => Some(Attribute {
+                key: path_to_string(&name_value.path),
+                value: Some(name_value.lit),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the doc comment in `attrs`, with the space following `///` removed from each line.
+pub fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<_> = attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(name_value)) => match name_value.lit {
+                Lit::Str(lit_str) => Some(lit_str.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| {
+            line.strip_prefix(' ')
+                .map(ToString::to_string)
+                .unwrap_or(line)
+        })
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+fn generic_types(ty: &Type) -> Option<(&Ident, Vec<&Type>)> {
+    let segment = match ty {
+        Type::Path(type_path) => type_path.path.segments.last()?,
+        _ => return None,
+    };
+    let types = match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) => arguments
+            .args
+            .iter()
+            .filter_map(|argument| match argument {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    Some((&segment.ident, types))
+}
+
+fn extract_common<T: Extract + ?Sized>(e: &mut T, target: Target, attrs: &[syn::Attribute]) {
+    let attributes = attributes(attrs);
+    if !attributes.is_empty() {
+        e.extract_attributes(&target, &attributes);
+    }
+    if let Some(doc) = doc_comment(attrs) {
+        e.extract_doc(&target, &doc);
+    }
+}
+
+fn extract_field<T: Extract + ?Sized>(
+    e: &mut T,
+    item: &Ident,
+    variant: Option<&Ident>,
+    field: &Field,
+) {
+    let target = Target {
+        item,
+        variant,
+        field: field.ident.as_ref(),
+    };
+    extract_common(e, target, &field.attrs);
+    e.extract_field(&target, field);
+
+    match generic_types(&field.ty) {
+        Some((ident, types)) if ident == "Option" && types.len() == 1 => {
+            e.extract_optional_field(&target, field, types[0]);
+        }
+        Some((ident, types))
+            if (ident == "HashMap" || ident == "BTreeMap" || ident == "IndexMap")
+                && types.len() == 2 =>
+        {
+            e.extract_map_field(&target, field, types[0], types[1]);
+        }
+        _ => {}
+    }
+}
+
+/// Visits `item`, which derives `ProtobufGen`, failing on the first part of it which cannot be
+/// converted, as `diagnostic::check_item` reports it.
+pub fn extract_item<T: Extract + ?Sized>(e: &mut T, item: &Item) -> syn::Result<()> {
+    match item {
+        Item::Struct(item_struct) => {
+            extract_message(e, item_struct)?;
+
+            let ident = &item_struct.ident;
+            extract_common(
+                e,
+                Target {
+                    item: ident,
+                    variant: None,
+                    field: None,
+                },
+                &item_struct.attrs,
+            );
+            for field in schema_fields(item_struct)
+                .iter()
+                .flat_map(|fields| &fields.named)
+            {
+                extract_field(e, ident, None, field);
+            }
+        }
+        Item::Enum(item_enum) => {
+            let is_unit = |v: &Variant| matches!(v.fields, Fields::Unit);
+
+            let is_unsupported = |v: &&Variant| matches!(&v.fields, Fields::Unnamed(fields_unnamed) if fields_unnamed.unnamed.len() != 1);
+            if let Some(variant) = item_enum.variants.iter().find(is_unsupported) {
+                return Err(Diagnostic::UnsupportedVariant.error(
+                    variant.ident.span(),
+                    "only unit, 'struct' with named fields and a single unnamed field can be converted to 'oneof' field",
+                ));
+            }
+
+            if let Some(optional_enum) = OptionalEnum::new(item_enum) {
+                e.extract_optional_enum(item_enum, &optional_enum);
+            } else if item_enum.variants.iter().all(is_unit) {
+                e.extract_enumerator(item_enum);
+            } else {
+                e.extract_one_of(item_enum);
+
+                for (i, variant) in item_enum.variants.iter().enumerate() {
+                    let kind = match &variant.fields {
+                        Fields::Named(fields_named) => OneOfVariantKind::Message(fields_named),
+                        Fields::Unnamed(fields_unnamed) => {
+                            OneOfVariantKind::Type(&fields_unnamed.unnamed[0].ty)
+                        }
+                        Fields::Unit => OneOfVariantKind::Unit,
+                    };
+                    let one_of_variant = OneOfVariant {
+                        variant,
+                        number: i + 1,
+                        kind,
+                    };
+                    e.extract_one_of_variant(item_enum, &one_of_variant);
+                    extract_nested_message(e, item_enum, variant)?;
+                }
+            }
+
+            let ident = &item_enum.ident;
+            extract_common(
+                e,
+                Target {
+                    item: ident,
+                    variant: None,
+                    field: None,
+                },
+                &item_enum.attrs,
+            );
+            for variant in &item_enum.variants {
+                extract_common(
+                    e,
+                    Target {
+                        item: ident,
+                        variant: Some(&variant.ident),
+                        field: None,
+                    },
+                    &variant.attrs,
+                );
+                if let Fields::Named(fields_named) = &variant.fields {
+                    for field in &fields_named.named {
+                        extract_field(e, ident, Some(&variant.ident), field);
+                    }
+                }
+            }
+        }
+        _ => {
+            return Err(Diagnostic::UnsupportedItem.error(
+                item.span(),
+                "only 'struct' and 'enum' can derive `ProtobufGen`",
+            ))
+        }
+    }
+    Ok(())
 }
 
 pub fn extract_nested_message<T: Extract + ?Sized>(
     e: &mut T,
     item_enum: &ItemEnum,
     variant: &Variant,
-) {
+) -> syn::Result<()> {
     match &variant.fields {
         Fields::Named(fields_named) => {
             e.extract_nested_message_with_fields_named(item_enum, variant, fields_named);
@@ -33,16 +358,36 @@ pub fn extract_nested_message<T: Extract + ?Sized>(
         Fields::Unit => {
             e.extract_nested_message_with_fields_unit(item_enum, variant);
         }
+        // a variant with a single unnamed field refers to its field type without nesting.
+        Fields::Unnamed(fields_unnamed) if fields_unnamed.unnamed.len() == 1 => {}
         _ => {
-            panic!(
-                "only unit and 'struct' with named fields can be converted to nested 'message': \"{:?}\"",
-                item_enum.ident
-            );
+            return Err(Diagnostic::UnsupportedVariant.error(
+                variant.ident.span(),
+                "only unit, 'struct' with named fields and a single unnamed field can be converted to nested 'message'",
+            ))
         }
     }
+    Ok(())
 }
 
-pub fn extract_message<T: Extract + ?Sized>(e: &mut T, item_struct: &ItemStruct) {
+pub fn extract_message<T: Extract + ?Sized>(
+    e: &mut T,
+    item_struct: &ItemStruct,
+) -> syn::Result<()> {
+    match schema_fields(item_struct) {
+        Some(fields_named) => {
+            e.extract_message_with_fields_named(item_struct, &fields_named);
+            Ok(())
+        }
+        None => Err(Diagnostic::UnsupportedItem.error(
+            item_struct.ident.span(),
+            "only 'struct' with named fields can be converted to 'message'",
+        )),
+    }
+}
+
+// Returns the fields of `item_struct` which appear in the schema, if any.
+fn schema_fields(item_struct: &ItemStruct) -> Option<FieldsNamed> {
     fn filter_field(field: &syn::Field) -> bool {
         if let syn::Visibility::Public(_) = field.vis {
             true
@@ -51,26 +396,25 @@ pub fn extract_message<T: Extract + ?Sized>(e: &mut T, item_struct: &ItemStruct)
         }
     }
 
-    if let syn::Fields::Named(fields_named) = &item_struct.fields {
-        let fields_named = FieldsNamed {
-            named: fields_named
-                .named
-                .iter()
-                .cloned()
-                .filter(filter_field)
-                .collect(),
-            ..fields_named.clone()
-        };
-        if !fields_named.named.is_empty() {
-            e.extract_message_with_fields_named(item_struct, &fields_named);
-            return;
+    match &item_struct.fields {
+        syn::Fields::Named(fields_named) => {
+            let fields_named = FieldsNamed {
+                named: fields_named
+                    .named
+                    .iter()
+                    .cloned()
+                    .filter(filter_field)
+                    .collect(),
+                ..fields_named.clone()
+            };
+            if fields_named.named.is_empty() {
+                None
+            } else {
+                Some(fields_named)
+            }
         }
+        _ => None,
     }
-
-    panic!(
-        "only 'struct' with named fields can be converted to 'message': \"{:?}\"",
-        item_struct.ident
-    );
 }
 
 fn path_to_string(path: &Path) -> String {
@@ -82,7 +426,7 @@ fn path_to_string(path: &Path) -> String {
     segments.join("::")
 }
 
-fn collect_items(file: &File) -> Vec<&Item> {
+fn collect_items(file: &File) -> Vec<Cow<'_, Item>> {
     let is_protobuf_gen = |nested_meta: &NestedMeta| {
         if let NestedMeta::Meta(Meta::Path(path)) = nested_meta {
             path_to_string(&path) == "ProtobufGen"
@@ -106,39 +450,22 @@ fn collect_items(file: &File) -> Vec<&Item> {
                     }
                 })
             }
+            Item::Macro(_) => true,
             _ => false,
         })
+        .filter_map(|item| match item {
+            Item::Macro(_) => {
+                Conversions::from_item_in(item, &file.items).map(|c| Cow::Owned(c.to_item()))
+            }
+            _ => Some(Cow::Borrowed(item)),
+        })
         .collect()
 }
 
-pub fn extract_from_file<T: Extract + ?Sized>(e: &mut T, file: &File) {
-    let items = collect_items(file);
-
-    for item in items {
-        match item {
-            Item::Struct(item_struct) => {
-                extract_message(e, item_struct);
-            }
-            Item::Enum(item_enum) => {
-                let is_unit = |v: &Variant| {
-                    if let Fields::Unit = v.fields {
-                        true
-                    } else {
-                        false
-                    }
-                };
-
-                if item_enum.variants.iter().all(is_unit) {
-                    e.extract_enumerator(item_enum);
-                } else {
-                    e.extract_one_of(item_enum);
-
-                    for variant in &item_enum.variants {
-                        extract_nested_message(e, item_enum, variant);
-                    }
-                }
-            }
-            _ => unreachable!(),
-        }
+/// Visits every item deriving `ProtobufGen` or defined with `conversions!` in `file`.
+pub fn extract_from_file<T: Extract + ?Sized>(e: &mut T, file: &File) -> syn::Result<()> {
+    for item in collect_items(file) {
+        extract_item(e, &item)?;
     }
+    Ok(())
 }
@@ -0,0 +1,205 @@
+//! Crate-wide defaults read from `protobuf-gen.conf` next to `Cargo.toml`, shared by
+//! `#[derive(ProtobufGen)]` and the schema generator so that both agree on the schema:
+//!
+//! ```text
+//! package_prefix = "corp"          # packages become `corp.<package>`
+//! default_int_encoding = "sint"    # "sint" (default), "int" or "fixed"
+//! naming = "aip"                   # "rust" (default) or "aip"
+//! optional_style = "proto3"        # "message" (default) or "proto3"
+//! ```
+//!
+//! The file is not TOML: each line is either blank, a `#` comment or a `key = "value"` pair,
+//! optionally followed by a comment. There are no sections, escapes or non-string values.
+//!
+//! With `naming = "aip"`, enum values are `UPPER_SNAKE_CASE` prefixed with the name of the enum,
+//! e.g. `AREA_CODE_SEOUL`, and `oneof` fields are `snake_case`. The zero value is not renamed to
+//! `*_UNSPECIFIED`, since it is the first variant of the Rust enum.
+//!
+//! `Option<T>` fields are plain fields of message types with `optional_style = "message"`, and
+//! `optional` fields of any type with `optional_style = "proto3"`, which requires `protoc`
+//! 3.12 or later. Fields of `#[protobuf_gen(optional)]` enums are `optional` with either style.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use heck::{ShoutySnakeCase, SnakeCase};
+
+pub const SETTINGS_FILE: &str = "protobuf-gen.conf";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntEncoding {
+    /// `sint32` and `sint64` for signed integers.
+    Sint,
+    /// `int32` and `int64` for signed integers.
+    Int,
+    /// `sfixed32`, `sfixed64`, `fixed32` and `fixed64` for all integers.
+    Fixed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Naming {
+    /// Names are the Rust identifiers.
+    Rust,
+    /// Names follow the protobuf style of https://google.aip.dev/126.
+    Aip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionalStyle {
+    Message,
+    Proto3,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub package_prefix: Option<String>,
+    pub default_int_encoding: IntEncoding,
+    pub naming: Naming,
+    pub optional_style: OptionalStyle,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            package_prefix: None,
+            default_int_encoding: IntEncoding::Sint,
+            naming: Naming::Rust,
+            optional_style: OptionalStyle::Message,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(PathBuf, io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(path, e) => write!(f, "failed to read {}: {}", path.display(), e),
+            SettingsError::Parse { line, message } => {
+                write!(f, "{} line {}: {}", SETTINGS_FILE, line, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl Settings {
+    /// Reads `protobuf-gen.conf` in the directory of the crate being built, i.e.
+    /// `CARGO_MANIFEST_DIR`, or returns the defaults if there is none.
+    pub fn from_manifest_dir() -> Result<Self, SettingsError> {
+        match Self::manifest_path() {
+            Some(path) => Self::load(path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Returns the path of `protobuf-gen.conf` of the crate being built, if there is one.
+    pub fn manifest_path() -> Option<PathBuf> {
+        let path = Path::new(&std::env::var_os("CARGO_MANIFEST_DIR")?).join(SETTINGS_FILE);
+        if path.exists() {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SettingsError> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path).map_err(|e| SettingsError::Io(path.to_path_buf(), e))?;
+        Self::parse(&s)
+    }
+
+    pub fn parse(s: &str) -> Result<Self, SettingsError> {
+        let mut settings = Self::default();
+        for (i, line) in s.lines().enumerate() {
+            let error = |message: String| SettingsError::Parse {
+                line: i + 1,
+                message,
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.find('=') {
+                Some(at) => (line[..at].trim(), line[at + 1..].trim()),
+                None => return Err(error(format!("expected `key = \"value\"`: `{}`", line))),
+            };
+            // the value ends at the closing quote, so that it may contain `#`
+            let (value, rest) = value
+                .strip_prefix('"')
+                .and_then(|value| {
+                    value
+                        .find('"')
+                        .map(|at| (&value[..at], value[at + 1..].trim()))
+                })
+                .ok_or_else(|| error(format!("expected a string value for `{}`", key)))?;
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return Err(error(format!(
+                    "unexpected `{}` after the value of `{}`",
+                    rest, key
+                )));
+            }
+            let invalid = || error(format!("invalid value for `{}`: \"{}\"", key, value));
+
+            match key {
+                "package_prefix" => settings.package_prefix = Some(value.to_string()),
+                "default_int_encoding" => {
+                    settings.default_int_encoding = match value {
+                        "sint" => IntEncoding::Sint,
+                        "int" => IntEncoding::Int,
+                        "fixed" => IntEncoding::Fixed,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "naming" => {
+                    settings.naming = match value {
+                        "rust" => Naming::Rust,
+                        "aip" => Naming::Aip,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "optional_style" => {
+                    settings.optional_style = match value {
+                        "message" => OptionalStyle::Message,
+                        "proto3" => OptionalStyle::Proto3,
+                        _ => return Err(invalid()),
+                    }
+                }
+                _ => return Err(error(format!("unknown key `{}`", key))),
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Returns the package of the schemas generated for the sources added to `package`.
+    pub fn package(&self, package: &str) -> String {
+        match &self.package_prefix {
+            Some(prefix) => format!("{}.{}", prefix, package),
+            None => package.to_string(),
+        }
+    }
+
+    /// Returns the name of `variant` in the protobuf enum converted from `item`.
+    pub fn enum_value_name(&self, item: &str, variant: &str) -> String {
+        match self.naming {
+            Naming::Rust => variant.to_string(),
+            Naming::Aip => format!("{}_{}", item, variant).to_shouty_snake_case(),
+        }
+    }
+
+    /// Returns the name of the `oneof` field converted from `variant`.
+    pub fn one_of_field_name(&self, variant: &str) -> String {
+        match self.naming {
+            Naming::Rust => variant.to_string(),
+            Naming::Aip => variant.to_snake_case(),
+        }
+    }
+}
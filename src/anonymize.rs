@@ -0,0 +1,301 @@
+//! Produces anonymized samples of production messages to seed test fixtures.
+//!
+//! `#[protobuf_gen(anonymize)]` implements `Anonymize` for a type. Its fields marked with
+//! `#[protobuf_gen(sensitive)]` are randomized while keeping their shape, and all other fields
+//! are kept as they are, so the distributions of enums and scalars are preserved. Fields of other
+//! types are anonymized recursively, so those types have to be annotated as well.
+//!
+//! With `#[protobuf_gen(anonymize, unique_by = "id")]`, `sample` keeps only the first message of
+//! those with the same `id`.
+//!
+//! Both attributes are also emitted to the schema as the options `(protobuf_gen.sensitive)` and
+//! `(protobuf_gen.unique_by)`, so `sample_encoded` anonymizes encodings with only the descriptor
+//! set of the schema, e.g. of messages captured from another service.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+
+use prost::encoding::{decode_key, encode_key, encode_varint, WireType};
+use prost_types::field_descriptor_proto::Type;
+
+use crate::descriptor::{DescriptorError, Descriptors};
+use crate::replay::take_value;
+use crate::rng::Rng;
+use crate::{Error, ProtobufGen};
+
+// Characters in these ranges are replaced with one in the same range, so that values keep their
+// scripts. The ranges have no unassigned code points.
+const SCRIPTS: &[(char, char)] = &[
+    ('a', 'z'),
+    ('A', 'Z'),
+    ('0', '9'),
+    ('\u{0410}', '\u{042f}'), // Cyrillic capital letters
+    ('\u{0430}', '\u{044f}'), // Cyrillic small letters
+    ('\u{3041}', '\u{3096}'), // Hiragana
+    ('\u{30a1}', '\u{30fa}'), // Katakana
+    ('\u{4e00}', '\u{9fff}'), // CJK unified ideographs
+    ('\u{ac00}', '\u{d7a3}'), // Hangul syllables
+];
+
+pub struct Anonymizer {
+    rng: Rng,
+}
+
+impl Anonymizer {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Rng::new(seed) }
+    }
+
+    /// Replaces every character but whitespace and ASCII punctuation with a random one, so that
+    /// the length and the format (e.g. of an email address) are kept. Letters and digits are
+    /// replaced with ones of the same class and script, and any other character with a lowercase
+    /// ASCII letter.
+    pub fn randomize_str(&mut self, s: &str) -> String {
+        s.chars()
+            .map(|c| {
+                if c.is_whitespace() || c.is_ascii_punctuation() {
+                    return c;
+                }
+                let (start, end) = SCRIPTS
+                    .iter()
+                    .copied()
+                    .find(|&(start, end)| (start..=end).contains(&c))
+                    .unwrap_or(('a', 'z'));
+                let n = u64::from(end as u32 - start as u32 + 1);
+                char::from_u32(start as u32 + self.rng.below(n) as u32).unwrap()
+            })
+            .collect()
+    }
+
+    pub fn randomize_bytes(&mut self, bytes: &[u8]) -> Vec<u8> {
+        bytes.iter().map(|_| self.rng.next_u64() as u8).collect()
+    }
+}
+
+pub trait Anonymize {
+    fn anonymize(&mut self, anonymizer: &mut Anonymizer);
+
+    /// The key for `sample` to skip messages it has already sampled, which is compared in full so
+    /// that distinct messages are never skipped.
+    fn unique_key(&self) -> Option<String> {
+        None
+    }
+}
+
+/// A type of `#[protobuf_gen(sensitive)]` fields.
+pub trait Sensitive {
+    fn randomize(&mut self, anonymizer: &mut Anonymizer);
+}
+
+macro_rules! impl_anonymize_noop {
+    ($($t:ty),*) => {
+        $(
+            impl Anonymize for $t {
+                fn anonymize(&mut self, _: &mut Anonymizer) {}
+            }
+        )*
+    };
+}
+
+impl_anonymize_noop!(bool, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64, String);
+
+impl<T: Anonymize> Anonymize for Vec<T> {
+    fn anonymize(&mut self, anonymizer: &mut Anonymizer) {
+        self.iter_mut().for_each(|x| x.anonymize(anonymizer));
+    }
+}
+
+impl<T: Anonymize> Anonymize for Option<T> {
+    fn anonymize(&mut self, anonymizer: &mut Anonymizer) {
+        if let Some(x) = self {
+            x.anonymize(anonymizer);
+        }
+    }
+}
+
+impl<T: Anonymize> Anonymize for Box<T> {
+    fn anonymize(&mut self, anonymizer: &mut Anonymizer) {
+        self.as_mut().anonymize(anonymizer);
+    }
+}
+
+impl<T: Anonymize + Eq + Hash> Anonymize for HashSet<T> {
+    fn anonymize(&mut self, anonymizer: &mut Anonymizer) {
+        *self = self
+            .drain()
+            .map(|mut x| {
+                x.anonymize(anonymizer);
+                x
+            })
+            .collect();
+    }
+}
+
+impl<K: Eq + Hash, V: Anonymize> Anonymize for HashMap<K, V> {
+    fn anonymize(&mut self, anonymizer: &mut Anonymizer) {
+        self.values_mut().for_each(|v| v.anonymize(anonymizer));
+    }
+}
+
+impl Sensitive for String {
+    fn randomize(&mut self, anonymizer: &mut Anonymizer) {
+        *self = anonymizer.randomize_str(self);
+    }
+}
+
+impl Sensitive for Vec<u8> {
+    fn randomize(&mut self, anonymizer: &mut Anonymizer) {
+        *self = anonymizer.randomize_bytes(self);
+    }
+}
+
+impl Sensitive for Vec<String> {
+    fn randomize(&mut self, anonymizer: &mut Anonymizer) {
+        self.iter_mut().for_each(|x| x.randomize(anonymizer));
+    }
+}
+
+impl<T: Sensitive> Sensitive for Option<T> {
+    fn randomize(&mut self, anonymizer: &mut Anonymizer) {
+        if let Some(x) = self {
+            x.randomize(anonymizer);
+        }
+    }
+}
+
+/// Returns the `Debug` representation of `value`, which tells apart the values of ids.
+#[doc(hidden)]
+pub fn unique_key<T: fmt::Debug>(value: &T) -> String {
+    format!("{:?}", value)
+}
+
+/// Decodes each of `encoded` as `T`, and returns the encodings of their anonymized copies.
+pub fn sample<'a, T, I>(encoded: I, anonymizer: &mut Anonymizer) -> Result<Vec<Vec<u8>>, Error>
+where
+    T: ProtobufGen<Error = Error> + Anonymize,
+    I: IntoIterator<Item = &'a [u8]>,
+{
+    let mut seen = HashSet::new();
+    let mut samples = Vec::new();
+    for mut encoded in encoded {
+        let mut message = T::from_protobuf(&mut encoded)?;
+        if let Some(key) = message.unique_key() {
+            if !seen.insert(key) {
+                continue;
+            }
+        }
+
+        message.anonymize(anonymizer);
+        let mut buffer = Vec::new();
+        message.to_protobuf(&mut buffer)?;
+        samples.push(buffer);
+    }
+    Ok(samples)
+}
+
+/// Decodes each of `encoded` with the descriptor of `message`, e.g. `"yellow_book.Designer"`, and
+/// returns their anonymized copies.
+///
+/// `string` and `bytes` fields with `(protobuf_gen.sensitive)` are randomized as by `Sensitive`,
+/// message fields are anonymized recursively, and all other fields, including unknown ones, are
+/// copied byte for byte. With `(protobuf_gen.unique_by)`, only the first message of those with
+/// the same encoding of the field is kept.
+pub fn sample_encoded<'a, I>(
+    descriptors: &Descriptors,
+    message: &str,
+    encoded: I,
+    anonymizer: &mut Anonymizer,
+) -> Result<Vec<Vec<u8>>, DescriptorError>
+where
+    I: IntoIterator<Item = &'a [u8]>,
+{
+    let unique_by = match descriptors.unique_by(message) {
+        Some(name) => {
+            let field = descriptors.message(message)?.field.iter().find(|f| f.name() == name);
+            Some(field.ok_or_else(|| {
+                DescriptorError::MalformedMessage(format!("unique_by names no field: `{}`", name))
+            })?)
+        }
+        None => None,
+    };
+
+    let mut seen = HashSet::new();
+    let mut samples = Vec::new();
+    for encoded in encoded {
+        if let Some(field) = unique_by {
+            if !seen.insert(field_encoding(encoded, field.number() as u32)?) {
+                continue;
+            }
+        }
+
+        let mut buffer = Vec::new();
+        rewrite(descriptors, message, encoded, anonymizer, &mut buffer)?;
+        samples.push(buffer);
+    }
+    Ok(samples)
+}
+
+// Returns the keys and values of every occurrence of the field `tag` in `bytes`.
+fn field_encoding(mut bytes: &[u8], tag: u32) -> Result<Vec<u8>, DescriptorError> {
+    let mut encoding = Vec::new();
+    while !bytes.is_empty() {
+        let start = bytes;
+        let (field_tag, wire_type) =
+            decode_key(&mut bytes).map_err(|e| DescriptorError::MalformedMessage(e.to_string()))?;
+        take_value(wire_type, &mut bytes).map_err(DescriptorError::MalformedMessage)?;
+        if field_tag == tag {
+            encoding.extend_from_slice(&start[..start.len() - bytes.len()]);
+        }
+    }
+    Ok(encoding)
+}
+
+// Writes the anonymized copy of `bytes`, an encoding of `message`, to `buf`.
+fn rewrite(
+    descriptors: &Descriptors,
+    message: &str,
+    mut bytes: &[u8],
+    anonymizer: &mut Anonymizer,
+    buf: &mut Vec<u8>,
+) -> Result<(), DescriptorError> {
+    let descriptor = descriptors.message(message)?;
+    while !bytes.is_empty() {
+        let start = bytes;
+        let (tag, wire_type) =
+            decode_key(&mut bytes).map_err(|e| DescriptorError::MalformedMessage(e.to_string()))?;
+        let value = take_value(wire_type, &mut bytes).map_err(DescriptorError::MalformedMessage)?;
+
+        let field = descriptor.field.iter().find(|field| field.number() as u32 == tag);
+        let replaced = match field {
+            Some(field) if wire_type == WireType::LengthDelimited => match field.r#type() {
+                Type::Message => {
+                    let mut nested = Vec::new();
+                    rewrite(descriptors, field.type_name(), value, anonymizer, &mut nested)?;
+                    Some(nested)
+                }
+                Type::String if descriptors.is_sensitive(message, field.name()) => {
+                    Some(match std::str::from_utf8(value) {
+                        Ok(s) => anonymizer.randomize_str(s).into_bytes(),
+                        Err(_) => anonymizer.randomize_bytes(value),
+                    })
+                }
+                Type::Bytes if descriptors.is_sensitive(message, field.name()) => {
+                    Some(anonymizer.randomize_bytes(value))
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+
+        match replaced {
+            Some(replaced) => {
+                encode_key(tag, wire_type, buf);
+                encode_varint(replaced.len() as u64, buf);
+                buf.extend_from_slice(&replaced);
+            }
+            None => buf.extend_from_slice(&start[..start.len() - bytes.len()]),
+        }
+    }
+    Ok(())
+}
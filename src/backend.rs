@@ -0,0 +1,131 @@
+//! Experimental: wire formats other than protobuf, generated from the same annotated types.
+//!
+//! `Config::generate` extracts the schemas of its sources, checks their `schema_version`s and
+//! writes the FFI header, and then hands the schemas to its `Backend`, which writes the output of
+//! its format, e.g. schema files and Rust bindings. `ProtobufBackend` is the default one. Another
+//! backend is set with `Config::backend`, and reads the schemas through `types`:
+//!
+//! ```ignore
+//! struct MessageList;
+//!
+//! impl Backend for MessageList {
+//!     fn generate(&self, config: &Config, _: &Settings, schemas: &[Schema]) -> Result<(), ConfigError> {
+//!         let names: Vec<_> = schemas.iter().flat_map(|schema| &schema.file.messages).map(|m| &m.name).collect();
+//!         fs::write(config.proto_target_dir.join("messages.txt"), format!("{:?}", names))?;
+//!         Ok(())
+//!     }
+//! }
+//! ```
+//!
+//! The derive only generates conversions to the proxies of `ProtobufBackend`, so the output of
+//! another backend is not converted to yet. This module, `types` and `Config::backend` are hidden
+//! from the docs, since backends read the internal model of the schemas, which may change in any
+//! release.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::parse::SchemaFile;
+use crate::print::SchemaPrinter;
+use crate::{status, version, Config, ConfigError, Settings};
+
+/// The schema of the types which land in the same file.
+pub struct Schema {
+    /// The path of the file relative to the target directory, named after the package or by
+    /// `#[protobuf_gen(file = "..")]`.
+    pub path: PathBuf,
+    pub file: SchemaFile,
+}
+
+pub trait Backend {
+    /// Writes the output for `schemas`, which are all the schemas of `config`, with the
+    /// crate-wide `settings`.
+    fn generate(
+        &self,
+        config: &Config,
+        settings: &Settings,
+        schemas: &[Schema],
+    ) -> Result<(), ConfigError>;
+}
+
+const PROTO3_OPTIONAL_ARG: &str = "--experimental_allow_proto3_optional";
+
+/// Writes `.proto` files to `Config::proto_target_dir` and, if set, compiles them with
+/// `prost-build` to proxies in `Config::proxy_target_dir` and to `Config::descriptor_set`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProtobufBackend;
+
+impl Backend for ProtobufBackend {
+    fn generate(
+        &self,
+        config: &Config,
+        _: &Settings,
+        schemas: &[Schema],
+    ) -> Result<(), ConfigError> {
+        let mut in_files = Vec::new();
+        for schema in schemas {
+            let file_path = config.proto_target_dir.join(&schema.path);
+            if let Some(dir_path) = file_path.parent() {
+                fs::create_dir_all(dir_path)?;
+            }
+            fs::write(&file_path, SchemaPrinter(&schema.file).to_string())?;
+            in_files.push(file_path);
+        }
+
+        if schemas.iter().any(|schema| schema.file.has_options()) {
+            let options_path = config.proto_target_dir.join(version::OPTIONS_PROTO_PATH);
+            fs::create_dir_all(options_path.parent().unwrap())?;
+            fs::write(options_path, version::OPTIONS_PROTO)?;
+        }
+
+        if schemas.iter().any(|schema| schema.file.has_status()) {
+            let status_path = config.proto_target_dir.join(status::STATUS_PROTO_PATH);
+            fs::create_dir_all(status_path.parent().unwrap())?;
+            fs::write(status_path, status::STATUS_PROTO)?;
+        }
+
+        // `optional` fields of `Option`s with `optional_style = "proto3"` and of optional enums
+        let proto3_optional = schemas.iter().any(|schema| schema.file.has_optional_fields());
+
+        // generate Rust bindings for protobuf
+        if let Some(ref proxy_target_dir) = config.proxy_target_dir {
+            fs::create_dir_all(proxy_target_dir)?;
+
+            let mut prost_config = prost_build::Config::new();
+            prost_config.type_attribute(".", "#[allow(clippy::large_enum_variant)]");
+            prost_config.out_dir(proxy_target_dir);
+            if proto3_optional {
+                prost_config.protoc_arg(PROTO3_OPTIONAL_ARG);
+            }
+            if let Some(ref path) = config.descriptor_set {
+                prost_config.file_descriptor_set_path(path);
+            }
+            prost_config.compile_protos(&in_files, &[PathBuf::from(&config.proto_target_dir)])?;
+        } else if let Some(ref path) = config.descriptor_set {
+            let mut protoc = Command::new(prost_build::protoc());
+            if proto3_optional {
+                protoc.arg(PROTO3_OPTIONAL_ARG);
+            }
+            let status = protoc
+                .arg("--include_imports")
+                .arg("-I")
+                .arg(&config.proto_target_dir)
+                .arg("-I")
+                .arg(prost_build::protoc_include())
+                .arg("-o")
+                .arg(path)
+                .args(&in_files)
+                .status()?;
+            if !status.success() {
+                return Err(io::Error::other(format!(
+                    "protoc failed to write a descriptor set: {}",
+                    status
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
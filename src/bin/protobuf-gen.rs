@@ -0,0 +1,111 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::process;
+
+use protobuf_gen::corpus::Corpus;
+use protobuf_gen::descriptor::Descriptors;
+use protobuf_gen::metrics::SizeMetrics;
+
+const USAGE: &str = "\
+usage:
+    protobuf-gen corpus --descriptor-set <FILE> --message <NAME> --count <N> [--seed <SEED>] --output <FILE>
+    protobuf-gen sizes --descriptor-set <FILE> --message <NAME> --input <FILE>
+
+commands:
+    corpus    writes <N> random length-delimited messages of <NAME>, e.g. `yellow_book.Person`,
+              using a descriptor set written by `Config::descriptor_set`
+    sizes     prints the bytes each field contributes to the length-delimited messages of <NAME>
+              in <FILE>, e.g. a corpus, with a histogram of their sizes per message";
+
+const CORPUS_OPTIONS: &[&str] = &["--descriptor-set", "--message", "--count", "--seed", "--output"];
+const SIZES_OPTIONS: &[&str] = &["--descriptor-set", "--message", "--input"];
+
+struct Options {
+    args: Vec<String>,
+}
+
+impl Options {
+    /// Parses `<option> <value>` pairs, failing on options other than `allowed` or repeated ones.
+    fn parse(args: Vec<String>, allowed: &[&str]) -> Result<Self, String> {
+        for (i, pair) in args.chunks(2).enumerate() {
+            let name = pair[0].as_str();
+            if !allowed.contains(&name) {
+                return Err(format!("unknown option {}", name));
+            }
+            if pair.len() < 2 {
+                return Err(format!("missing the value of {}", name));
+            }
+            if args.chunks(2).take(i).any(|pair| pair[0] == name) {
+                return Err(format!("repeated {}", name));
+            }
+        }
+        Ok(Self { args })
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .step_by(2)
+            .position(|arg| arg == name)
+            .and_then(|i| self.args.get(2 * i + 1))
+            .map(String::as_str)
+    }
+
+    fn required(&self, name: &str) -> Result<&str, String> {
+        self.value(name).ok_or_else(|| format!("missing {}", name))
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str, default: Option<T>) -> Result<T, String> {
+        match self.value(name) {
+            Some(value) => value.parse().map_err(|_| format!("invalid {}: {}", name, value)),
+            None => default.ok_or_else(|| format!("missing {}", name)),
+        }
+    }
+}
+
+fn corpus(options: &Options) -> Result<(), String> {
+    let descriptors =
+        Descriptors::load(options.required("--descriptor-set")?).map_err(|e| e.to_string())?;
+    let message = options.required("--message")?;
+    let count = options.number("--count", None)?;
+    let seed = options.number("--seed", Some(0))?;
+    let output = options.required("--output")?;
+
+    let mut w = BufWriter::new(File::create(output).map_err(|e| e.to_string())?);
+    Corpus::new(&descriptors, seed)
+        .write_length_delimited(message, count, &mut w)
+        .map_err(|e| e.to_string())?;
+    w.flush().map_err(|e| e.to_string())
+}
+
+fn sizes(options: &Options) -> Result<(), String> {
+    let descriptors =
+        Descriptors::load(options.required("--descriptor-set")?).map_err(|e| e.to_string())?;
+    let message = options.required("--message")?;
+    let input = fs::read(options.required("--input")?).map_err(|e| e.to_string())?;
+
+    let mut metrics = SizeMetrics::new(&descriptors, message).map_err(|e| e.to_string())?;
+    metrics.add_corpus(&input).map_err(|e| e.to_string())?;
+    print!("{}", metrics);
+    Ok(())
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let command = args.next();
+    let args = args.collect();
+
+    let result = match command.as_deref() {
+        Some("corpus") => Options::parse(args, CORPUS_OPTIONS).and_then(|options| corpus(&options)),
+        Some("sizes") => Options::parse(args, SIZES_OPTIONS).and_then(|options| sizes(&options)),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}\n\n{}", e, USAGE);
+        process::exit(1);
+    }
+}
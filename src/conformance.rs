@@ -0,0 +1,177 @@
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::{Error, ProtobufGen};
+
+static DESCRIPTOR_SET_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Checks encodings produced by generated conversions against the emitted `.proto` schemas with
+/// external decoders, which catches wire type mismatches that Rust-only roundtrips cannot see.
+///
+/// `protoc` is the one bundled with `prost-build` unless `PROTOC` is set. `protoscope` is used
+/// only when it is found on `PATH`.
+pub struct Conformance {
+    proto_dir: PathBuf,
+}
+
+impl Conformance {
+    pub fn new<P: Into<PathBuf>>(proto_dir: P) -> Self {
+        Self { proto_dir: proto_dir.into() }
+    }
+
+    /// Encodes `value` and decodes the result as `message` (e.g. `"yellow_book.Person"`) defined
+    /// in `proto_file`, returning the text format printed by `protoc`.
+    ///
+    /// `protoc` prints a field whose wire type does not match the schema as an unknown field, so
+    /// any unknown field in the output is reported as an error too.
+    pub fn check<T: ProtobufGen<Error = Error>>(
+        &self,
+        value: T,
+        proto_file: &str,
+        message: &str,
+    ) -> Result<String, Error> {
+        let mut buffer = Vec::new();
+        value.to_protobuf(&mut buffer)?;
+
+        if let Some(output) = self.protoscope_decode(proto_file, message, &buffer)? {
+            debug!("protoscope:\n{}", output);
+        }
+        let output = self.protoc_decode(proto_file, message, &buffer)?;
+        if let Some(line) = output.lines().find(|line| is_unknown_field(line)) {
+            return Err(Error::new_rejected_encoding(
+                "protoc",
+                message,
+                format!("unknown or mistyped field `{}`", line.trim()),
+            ));
+        }
+        Ok(output)
+    }
+
+    pub fn protoc_decode(
+        &self,
+        proto_file: &str,
+        message: &str,
+        bytes: &[u8],
+    ) -> Result<String, Error> {
+        let mut cmd = self.protoc();
+        cmd.arg(format!("--decode={}", message)).arg(proto_file);
+        run(cmd, bytes)
+            .map(|output| String::from_utf8_lossy(&output).into_owned())
+            .map_err(|e| Error::new_rejected_encoding("protoc", message, e))
+    }
+
+    /// Disassembles `bytes` as `message` with `protoscope`, returning its text, which must
+    /// assemble back to `bytes`.
+    ///
+    /// Returns `None` if `protoscope` is not installed.
+    pub fn protoscope_decode(
+        &self,
+        proto_file: &str,
+        message: &str,
+        bytes: &[u8],
+    ) -> Result<Option<String>, Error> {
+        let protoscope = match find_on_path("protoscope") {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        // unique per call, since checks run in parallel in tests
+        let descriptor_set = std::env::temp_dir().join(format!(
+            "protobuf_gen_conformance_{}_{}.pb",
+            std::process::id(),
+            DESCRIPTOR_SET_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut cmd = self.protoc();
+        cmd.arg("--include_imports").arg("-o").arg(&descriptor_set).arg(proto_file);
+        run(cmd, &[]).map_err(|e| Error::new_rejected_encoding("protoc", message, e))?;
+
+        let mut cmd = Command::new(&protoscope);
+        cmd.arg("-s").arg("-descriptor-set").arg(&descriptor_set).arg("-message-type").arg(message);
+        let output = run(cmd, bytes);
+        let _ = std::fs::remove_file(descriptor_set);
+        let output = output.map_err(|e| Error::new_rejected_encoding("protoscope", message, e))?;
+
+        let reassembled = run(Command::new(&protoscope), &output)
+            .map_err(|e| Error::new_rejected_encoding("protoscope", message, e))?;
+        if reassembled != bytes {
+            return Err(Error::new_rejected_encoding(
+                "protoscope",
+                message,
+                "the disassembly does not assemble back to the encoding",
+            ));
+        }
+        Ok(Some(String::from_utf8_lossy(&output).into_owned()))
+    }
+
+    fn protoc(&self) -> Command {
+        let mut cmd = Command::new(prost_build::protoc());
+        // for schemas generated with `optional_style = "proto3"`
+        cmd.arg("--experimental_allow_proto3_optional");
+        cmd.arg(include_arg(&self.proto_dir)).arg(include_arg(&prost_build::protoc_include()));
+        cmd
+    }
+}
+
+// Unknown fields are printed with their field number instead of their name.
+fn is_unknown_field(line: &str) -> bool {
+    let key = line.trim_start().split([':', ' ']).next().unwrap_or_default();
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_digit())
+}
+
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+fn include_arg(path: &Path) -> std::ffi::OsString {
+    let mut arg = OsStr::new("-I").to_os_string();
+    arg.push(path);
+    arg
+}
+
+#[derive(Debug)]
+enum Failure {
+    Spawn(io::Error),
+    Exit(String),
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Spawn(e) => write!(f, "failed to run: {}", e),
+            Failure::Exit(stderr) => write!(f, "{}", stderr.trim()),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+// Returns the stdout of `cmd` run with `input` as its stdin.
+fn run(mut cmd: Command, input: &[u8]) -> Result<Vec<u8>, Failure> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(Failure::Spawn)?;
+    let mut stdin = child.stdin.take().unwrap();
+
+    // written by another thread, since the child may block on a full stdout before reading all of
+    // its input
+    let (written, output) = thread::scope(|scope| {
+        let writer = scope.spawn(move || stdin.write_all(input));
+        let output = child.wait_with_output();
+        (writer.join().unwrap(), output)
+    });
+    let output = output.map_err(Failure::Spawn)?;
+    if !output.status.success() {
+        return Err(Failure::Exit(String::from_utf8_lossy(&output.stderr).into_owned()));
+    }
+    written.map_err(Failure::Spawn)?;
+    Ok(output.stdout)
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use prost::encoding::{encode_key, encode_varint, WireType};
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::FieldDescriptorProto;
+
+use crate::descriptor::{DescriptorError, Descriptors};
+use crate::rng::Rng;
+
+// Message fields nested deeper than this are left unset, so recursive messages terminate.
+const MAX_DEPTH: usize = 8;
+const MAX_REPEATED: u64 = 4;
+const MAX_LEN: u64 = 16;
+
+/// Generates random, reproducible encodings of messages from their descriptors.
+///
+/// Every field is set, exactly one field of every `oneof` is set, and every repeated field has
+/// up to 4 elements. Scalars are uniformly random over their types, and enums only take their
+/// defined values.
+pub struct Corpus<'a> {
+    descriptors: &'a Descriptors,
+    rng: Rng,
+}
+
+impl<'a> Corpus<'a> {
+    pub fn new(descriptors: &'a Descriptors, seed: u64) -> Self {
+        Self { descriptors, rng: Rng::new(seed) }
+    }
+
+    pub fn message(&mut self, name: &str) -> Result<Vec<u8>, DescriptorError> {
+        let mut buf = Vec::new();
+        self.encode_message(name, 0, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Writes `count` messages to `w`, each prefixed with the length of its encoding as a varint.
+    pub fn write_length_delimited<W: Write>(
+        &mut self,
+        name: &str,
+        count: usize,
+        w: &mut W,
+    ) -> Result<(), DescriptorError> {
+        for _ in 0..count {
+            let message = self.message(name)?;
+            let mut length = Vec::new();
+            encode_varint(message.len() as u64, &mut length);
+            w.write_all(&length)?;
+            w.write_all(&message)?;
+        }
+        Ok(())
+    }
+
+    fn encode_message(
+        &mut self,
+        name: &str,
+        depth: usize,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), DescriptorError> {
+        let descriptors = self.descriptors;
+        let message = descriptors.message(name)?;
+
+        let mut oneofs: BTreeMap<i32, Vec<&FieldDescriptorProto>> = BTreeMap::new();
+        for field in &message.field {
+            match field.oneof_index {
+                Some(index) => oneofs.entry(index).or_default().push(field),
+                None => self.encode_field(field, depth, buf)?,
+            }
+        }
+        for fields in oneofs.values() {
+            let field = fields[self.rng.below(fields.len() as u64) as usize];
+            self.encode_field(field, depth, buf)?;
+        }
+        Ok(())
+    }
+
+    fn encode_field(
+        &mut self,
+        field: &FieldDescriptorProto,
+        depth: usize,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), DescriptorError> {
+        if field.r#type() == Type::Message && depth >= MAX_DEPTH {
+            return Ok(());
+        }
+
+        let count =
+            if field.label() == Label::Repeated { self.rng.below(MAX_REPEATED + 1) } else { 1 };
+        for _ in 0..count {
+            self.encode_value(field, depth, buf)?;
+        }
+        Ok(())
+    }
+
+    fn encode_value(
+        &mut self,
+        field: &FieldDescriptorProto,
+        depth: usize,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), DescriptorError> {
+        let tag = field.number() as u32;
+        let rng = &mut self.rng;
+        match field.r#type() {
+            Type::Int32 => {
+                encode_key(tag, WireType::Varint, buf);
+                encode_varint(rng.next_u64() as i32 as i64 as u64, buf);
+            }
+            Type::Int64 | Type::Uint64 => {
+                encode_key(tag, WireType::Varint, buf);
+                encode_varint(rng.next_u64(), buf);
+            }
+            Type::Uint32 => {
+                encode_key(tag, WireType::Varint, buf);
+                encode_varint(rng.next_u64() as u32 as u64, buf);
+            }
+            Type::Sint32 => {
+                let n = rng.next_u64() as i32;
+                encode_key(tag, WireType::Varint, buf);
+                encode_varint(((n << 1) ^ (n >> 31)) as u32 as u64, buf);
+            }
+            Type::Sint64 => {
+                let n = rng.next_u64() as i64;
+                encode_key(tag, WireType::Varint, buf);
+                encode_varint(((n << 1) ^ (n >> 63)) as u64, buf);
+            }
+            Type::Bool => {
+                encode_key(tag, WireType::Varint, buf);
+                encode_varint(rng.below(2), buf);
+            }
+            Type::Enum => {
+                let values = &self.descriptors.enumeration(field.type_name())?.value;
+                let value = values[self.rng.below(values.len() as u64) as usize].number();
+                encode_key(tag, WireType::Varint, buf);
+                encode_varint(value as i64 as u64, buf);
+            }
+            Type::Fixed32 | Type::Sfixed32 | Type::Float => {
+                encode_key(tag, WireType::ThirtyTwoBit, buf);
+                buf.extend_from_slice(&(rng.next_u64() as u32).to_le_bytes());
+            }
+            Type::Fixed64 | Type::Sfixed64 | Type::Double => {
+                encode_key(tag, WireType::SixtyFourBit, buf);
+                buf.extend_from_slice(&rng.next_u64().to_le_bytes());
+            }
+            Type::String => {
+                const ALPHANUMERIC: &[u8] =
+                    b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+                let len = rng.below(MAX_LEN + 1);
+                encode_key(tag, WireType::LengthDelimited, buf);
+                encode_varint(len, buf);
+                for _ in 0..len {
+                    buf.push(ALPHANUMERIC[rng.below(ALPHANUMERIC.len() as u64) as usize]);
+                }
+            }
+            Type::Bytes => {
+                let len = rng.below(MAX_LEN + 1);
+                encode_key(tag, WireType::LengthDelimited, buf);
+                encode_varint(len, buf);
+                for _ in 0..len {
+                    buf.push(rng.next_u64() as u8);
+                }
+            }
+            Type::Message => {
+                let mut nested = Vec::new();
+                self.encode_message(field.type_name(), depth + 1, &mut nested)?;
+                encode_key(tag, WireType::LengthDelimited, buf);
+                encode_varint(nested.len() as u64, buf);
+                buf.extend_from_slice(&nested);
+            }
+            Type::Group => return Err(DescriptorError::UnsupportedGroup(field.name().to_string())),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use prost::Message;
+use prost_types::{DescriptorProto, EnumDescriptorProto, FileDescriptorSet};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DescriptorError {
+    #[error("failed to read a file.")]
+    IoError(#[from] io::Error),
+    #[error("failed to decode a descriptor set.")]
+    DecodeError(#[from] prost::DecodeError),
+    #[error("unknown message `{0}`.")]
+    UnknownMessage(String),
+    #[error("unknown enum `{0}`.")]
+    UnknownEnum(String),
+    #[error("a corpus ends in the middle of a message.")]
+    TruncatedCorpus,
+    #[error("malformed message: {0}.")]
+    MalformedMessage(String),
+    #[error("groups are not supported: `{0}`.")]
+    UnsupportedGroup(String),
+}
+
+/// The messages and enums of a descriptor set written with `Config::descriptor_set`, by their
+/// fully-qualified names without the leading dot, e.g. `yellow_book.Person`.
+#[derive(Debug, Default)]
+pub struct Descriptors {
+    messages: HashMap<String, DescriptorProto>,
+    enums: HashMap<String, EnumDescriptorProto>,
+    // fully-qualified names of fields with `(protobuf_gen.sensitive)`, e.g. `yellow_book.Designer.name`
+    sensitive: HashSet<String>,
+    unique_by: HashMap<String, String>,
+}
+
+// Only the options of `protobuf_gen/options.proto` in a descriptor set, which `prost_types` drops
+// as unknown fields of `MessageOptions` and `FieldOptions`.
+#[derive(Clone, PartialEq, Message)]
+struct OptionsFileSet {
+    #[prost(message, repeated, tag = "1")]
+    file: Vec<OptionsFile>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct OptionsFile {
+    #[prost(string, optional, tag = "2")]
+    package: Option<String>,
+    #[prost(message, repeated, tag = "4")]
+    message_type: Vec<OptionsMessage>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct OptionsMessage {
+    #[prost(string, optional, tag = "1")]
+    name: Option<String>,
+    #[prost(message, repeated, tag = "2")]
+    field: Vec<OptionsField>,
+    #[prost(message, repeated, tag = "3")]
+    nested_type: Vec<OptionsMessage>,
+    #[prost(message, optional, tag = "7")]
+    options: Option<MessageOptions>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MessageOptions {
+    #[prost(string, optional, tag = "50001")]
+    unique_by: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct OptionsField {
+    #[prost(string, optional, tag = "1")]
+    name: Option<String>,
+    #[prost(message, optional, tag = "8")]
+    options: Option<FieldOptions>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FieldOptions {
+    #[prost(bool, optional, tag = "50000")]
+    sensitive: Option<bool>,
+}
+
+impl Descriptors {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, DescriptorError> {
+        Self::decode(&fs::read(path)?)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DescriptorError> {
+        let file_descriptor_set = FileDescriptorSet::decode(bytes)?;
+
+        let mut descriptors = Self::default();
+        for file in file_descriptor_set.file {
+            let prefix = file.package.unwrap_or_default();
+            descriptors.collect(&prefix, file.message_type, file.enum_type);
+        }
+        for file in OptionsFileSet::decode(bytes)?.file {
+            let prefix = file.package.unwrap_or_default();
+            descriptors.collect_options(&prefix, file.message_type);
+        }
+        Ok(descriptors)
+    }
+
+    fn collect_options(&mut self, prefix: &str, messages: Vec<OptionsMessage>) {
+        for m in messages {
+            let name = match m.name {
+                Some(name) if !prefix.is_empty() => format!("{}.{}", prefix, name),
+                name => name.unwrap_or_default(),
+            };
+            for field in m.field {
+                if field.options.and_then(|options| options.sensitive) == Some(true) {
+                    self.sensitive.insert(format!("{}.{}", name, field.name.unwrap_or_default()));
+                }
+            }
+            if let Some(unique_by) = m.options.and_then(|options| options.unique_by) {
+                self.unique_by.insert(name.clone(), unique_by);
+            }
+            self.collect_options(&name, m.nested_type);
+        }
+    }
+
+    fn collect(
+        &mut self,
+        prefix: &str,
+        messages: Vec<DescriptorProto>,
+        enums: Vec<EnumDescriptorProto>,
+    ) {
+        let full_name = |name: &Option<String>| {
+            let name = name.as_deref().unwrap_or_default();
+            if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", prefix, name)
+            }
+        };
+
+        for e in enums {
+            self.enums.insert(full_name(&e.name), e);
+        }
+        for mut m in messages {
+            let name = full_name(&m.name);
+            let nested_messages = std::mem::take(&mut m.nested_type);
+            let nested_enums = std::mem::take(&mut m.enum_type);
+            self.collect(&name, nested_messages, nested_enums);
+            self.messages.insert(name, m);
+        }
+    }
+
+    /// Returns the message named `name`, which may start with a dot as in `type_name` of fields.
+    pub fn message(&self, name: &str) -> Result<&DescriptorProto, DescriptorError> {
+        let name = name.trim_start_matches('.');
+        self.messages.get(name).ok_or_else(|| DescriptorError::UnknownMessage(name.to_string()))
+    }
+
+    /// Returns the enum named `name`, which may start with a dot as in `type_name` of fields.
+    pub fn enumeration(&self, name: &str) -> Result<&EnumDescriptorProto, DescriptorError> {
+        let name = name.trim_start_matches('.');
+        self.enums.get(name).ok_or_else(|| DescriptorError::UnknownEnum(name.to_string()))
+    }
+
+    /// Returns whether the field `field` of the message `message` has `(protobuf_gen.sensitive)`.
+    pub fn is_sensitive(&self, message: &str, field: &str) -> bool {
+        self.sensitive.contains(&format!("{}.{}", message.trim_start_matches('.'), field))
+    }
+
+    /// Returns the field named by `(protobuf_gen.unique_by)` of the message `message`, if any.
+    pub fn unique_by(&self, message: &str) -> Option<&str> {
+        self.unique_by.get(message.trim_start_matches('.')).map(String::as_str)
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, HashMap};
 use std::io::Error as IoError;
 
 use prost::{DecodeError, EncodeError};
@@ -17,6 +18,20 @@ pub enum Error {
     ProstDecodeError(#[from] DecodeError),
     #[error("failed to convert `{0}`")]
     TryFromError(String, #[source] Box<dyn ::std::error::Error + Sync + Send + 'static>),
+    #[error("no keyring is set to use key `{0}`")]
+    NoKeyring(String),
+    #[error("keyring failed with key `{0}`")]
+    KeyringError(String, #[source] Box<dyn ::std::error::Error + Sync + Send + 'static>),
+    #[error("content `{0}` does not match its hash")]
+    IntegrityError(String),
+    #[error("invalid wire struct `{0}`: {1}")]
+    WireStructError(String, String),
+    #[error("`{0}` failed to decode `{1}`")]
+    RejectedEncoding(
+        String,
+        String,
+        #[source] Box<dyn ::std::error::Error + Sync + Send + 'static>,
+    ),
 }
 
 impl Error {
@@ -37,4 +52,82 @@ impl Error {
     ) -> Self {
         Self::TryFromError(ident.to_string(), e.into())
     }
+
+    pub fn new_no_keyring<T: ToString>(key: T) -> Self {
+        Self::NoKeyring(key.to_string())
+    }
+
+    pub fn new_keyring_error<
+        T: ToString,
+        E: Into<Box<dyn ::std::error::Error + Sync + Send + 'static>>,
+    >(
+        key: T,
+        e: E,
+    ) -> Self {
+        Self::KeyringError(key.to_string(), e.into())
+    }
+
+    pub fn new_integrity_error<T: ToString>(hash: T) -> Self {
+        Self::IntegrityError(hash.to_string())
+    }
+
+    pub fn new_wire_struct_error<T: ToString, U: ToString>(ident: T, reason: U) -> Self {
+        Self::WireStructError(ident.to_string(), reason.to_string())
+    }
+
+    pub fn new_rejected_encoding<
+        T: ToString,
+        U: ToString,
+        E: Into<Box<dyn ::std::error::Error + Sync + Send + 'static>>,
+    >(
+        decoder: T,
+        message: U,
+        e: E,
+    ) -> Self {
+        Self::RejectedEncoding(decoder.to_string(), message.to_string(), e.into())
+    }
+}
+
+/// Converts `field` of a proxy with `convert`, or returns `None` if the field is not set, i.e. it
+/// is a message field of `None`, for fields of `#[protobuf_gen(default)]` types, which take the
+/// unset fields from a single `Default::default()`. Set fields still fail to convert if their
+/// nested messages have unset fields, unless the nested message type has
+/// `#[protobuf_gen(default)]` as well.
+#[doc(hidden)]
+pub fn if_set<P: ProxyField, T, E>(
+    field: P,
+    convert: impl FnOnce(P) -> Result<T, E>,
+) -> Result<Option<T>, E> {
+    if field.is_unset() {
+        Ok(None)
+    } else {
+        convert(field).map(Some)
+    }
 }
+
+/// The type of a field of a proxy, which is only unset if it is an `Option`, since other fields
+/// have no presence.
+#[doc(hidden)]
+pub trait ProxyField {
+    fn is_unset(&self) -> bool {
+        false
+    }
+}
+
+impl<T> ProxyField for Option<T> {
+    fn is_unset(&self) -> bool {
+        self.is_none()
+    }
+}
+
+impl ProxyField for bool {}
+impl ProxyField for i32 {}
+impl ProxyField for i64 {}
+impl ProxyField for u32 {}
+impl ProxyField for u64 {}
+impl ProxyField for f32 {}
+impl ProxyField for f64 {}
+impl ProxyField for String {}
+impl<T> ProxyField for Vec<T> {}
+impl<K, V> ProxyField for HashMap<K, V> {}
+impl<K, V> ProxyField for BTreeMap<K, V> {}
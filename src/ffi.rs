@@ -0,0 +1,205 @@
+//! C ABI shims for types annotated with `#[protobuf_gen(ffi)]`.
+//!
+//! For a type `City` in the package `yellow_book`, the derive exports these functions, with the
+//! package and the name of the type in snake case as their prefix:
+//!
+//! - `yellow_book_city_decode` decodes bytes into a new `City` owned by the caller, or returns
+//!   null,
+//! - `yellow_book_city_encode` encodes a `City` into a `Buffer` owned by the caller, which needs
+//!   `Clone`,
+//! - `yellow_book_city_free` drops a `City` returned by `yellow_book_city_decode`.
+//!
+//! The C header declares `City` as the opaque struct `yellow_book_City`. The package is read from
+//! the index `Config::generate` writes in the build script, so the type has to be generated
+//! there, or the derive fails. A panic never unwinds into the caller: decoding returns null and
+//! encoding returns `Status::Panicked` instead.
+//!
+//! A `Buffer` is freed with `protobuf_gen_buffer_free`. `Config::ffi_header` writes the C header
+//! declaring these functions.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::{mem, ptr, slice};
+
+use crate::extract::index::{ffi_prefix, ffi_type};
+use crate::extract::{Attribute, Extract, Target};
+use crate::{Error, ProtobufGen};
+
+/// Bytes allocated by Rust.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Buffer {
+    pub data: *mut u8,
+    pub len: usize,
+    pub capacity: usize,
+}
+
+impl Buffer {
+    pub fn empty() -> Self {
+        Self { data: ptr::null_mut(), len: 0, capacity: 0 }
+    }
+
+    pub fn from_vec(mut bytes: Vec<u8>) -> Self {
+        let buffer =
+            Self { data: bytes.as_mut_ptr(), len: bytes.len(), capacity: bytes.capacity() };
+        mem::forget(bytes);
+        buffer
+    }
+
+    /// # Safety
+    ///
+    /// `self` must be returned by `Buffer::from_vec`.
+    pub unsafe fn into_vec(self) -> Vec<u8> {
+        if self.data.is_null() {
+            Vec::new()
+        } else {
+            Vec::from_raw_parts(self.data, self.len, self.capacity)
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok = 0,
+    NullPointer = 1,
+    Failed = 2,
+    Panicked = 3,
+}
+
+/// # Safety
+///
+/// `buffer` must be returned by an `_encode` function, and must not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn protobuf_gen_buffer_free(buffer: Buffer) {
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(buffer.into_vec())));
+}
+
+#[doc(hidden)]
+pub unsafe fn decode<T: ProtobufGen<Error = Error>>(data: *const u8, len: usize) -> *mut T {
+    let bytes = match (data.is_null(), len) {
+        (_, 0) => &[][..],
+        (true, _) => return ptr::null_mut(),
+        (false, _) => slice::from_raw_parts(data, len),
+    };
+    match panic::catch_unwind(AssertUnwindSafe(|| T::from_protobuf(&mut &bytes[..]))) {
+        Ok(Ok(value)) => Box::into_raw(Box::new(value)),
+        Ok(Err(e)) => {
+            debug!("failed to decode {}: {}", std::any::type_name::<T>(), e);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            debug!("panicked to decode {}", std::any::type_name::<T>());
+            ptr::null_mut()
+        }
+    }
+}
+
+#[doc(hidden)]
+pub unsafe fn encode<T: ProtobufGen<Error = Error> + Clone>(
+    value: *const T,
+    out: *mut Buffer,
+) -> Status {
+    if value.is_null() || out.is_null() {
+        return Status::NullPointer;
+    }
+
+    let mut bytes = Vec::new();
+    match panic::catch_unwind(AssertUnwindSafe(|| (*value).clone().to_protobuf(&mut bytes))) {
+        Ok(Ok(())) => {
+            out.write(Buffer::from_vec(bytes));
+            Status::Ok
+        }
+        Ok(Err(e)) => {
+            debug!("failed to encode {}: {}", std::any::type_name::<T>(), e);
+            Status::Failed
+        }
+        Err(_) => {
+            debug!("panicked to encode {}", std::any::type_name::<T>());
+            Status::Panicked
+        }
+    }
+}
+
+#[doc(hidden)]
+pub unsafe fn free<T>(value: *mut T) {
+    if !value.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(value))));
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct HeaderBuilder {
+    /// The package of the sources being extracted.
+    pub(crate) package: String,
+    // the prefixes of the functions and the names of the C structs
+    types: BTreeSet<(String, String)>,
+}
+
+impl Extract for HeaderBuilder {
+    fn extract_attributes(&mut self, target: &Target, attributes: &[Attribute]) {
+        if target.variant.is_none()
+            && target.field.is_none()
+            && attributes.iter().any(|attribute| attribute.key == "ffi")
+        {
+            let name = target.item.to_string();
+            self.types.insert((ffi_prefix(&self.package, &name), ffi_type(&self.package, &name)));
+        }
+    }
+}
+
+impl HeaderBuilder {
+    pub(crate) fn build(&self) -> String {
+        let mut header = String::from(HEADER_PRELUDE);
+        for (prefix, name) in &self.types {
+            writeln!(header).unwrap();
+            writeln!(header, "typedef struct {0} {0};", name).unwrap();
+            writeln!(header, "{} *{}_decode(const uint8_t *data, size_t len);", name, prefix)
+                .unwrap();
+            writeln!(
+                header,
+                "ProtobufGenStatus {}_encode(const {} *value, ProtobufGenBuffer *out);",
+                prefix, name
+            )
+            .unwrap();
+            writeln!(header, "void {}_free({} *value);", prefix, name).unwrap();
+        }
+        header + HEADER_EPILOGUE
+    }
+}
+
+const HEADER_PRELUDE: &str = r#"/* Generated by protobuf-gen. */
+#ifndef PROTOBUF_GEN_FFI_H
+#define PROTOBUF_GEN_FFI_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct {
+    uint8_t *data;
+    size_t len;
+    size_t capacity;
+} ProtobufGenBuffer;
+
+typedef enum {
+    PROTOBUF_GEN_OK = 0,
+    PROTOBUF_GEN_NULL_POINTER = 1,
+    PROTOBUF_GEN_FAILED = 2,
+    PROTOBUF_GEN_PANICKED = 3,
+} ProtobufGenStatus;
+
+void protobuf_gen_buffer_free(ProtobufGenBuffer buffer);
+"#;
+
+const HEADER_EPILOGUE: &str = r#"
+#ifdef __cplusplus
+}
+#endif
+
+#endif
+"#;
@@ -0,0 +1,70 @@
+use std::sync::RwLock;
+
+use crate::Error;
+
+pub type KeyringError = Box<dyn ::std::error::Error + Sync + Send + 'static>;
+
+/// Encrypts and decrypts fields annotated with `#[protobuf_gen(encrypt = "<key>")]`.
+///
+/// Such fields are stored as `bytes` in the schema, and generated conversions pass the name of
+/// the key to the keyring set by `set_keyring`.
+pub trait Keyring: Send + Sync {
+    fn encrypt(&self, key: &str, plaintext: &[u8]) -> Result<Vec<u8>, KeyringError>;
+    fn decrypt(&self, key: &str, ciphertext: &[u8]) -> Result<Vec<u8>, KeyringError>;
+}
+
+/// A field type which can be encrypted, whose default is decoded from an unset field.
+pub trait Plaintext: Sized + Default {
+    fn into_plaintext(self) -> Vec<u8>;
+    fn from_plaintext(plaintext: Vec<u8>) -> Result<Self, KeyringError>;
+}
+
+impl Plaintext for Vec<u8> {
+    fn into_plaintext(self) -> Vec<u8> {
+        self
+    }
+
+    fn from_plaintext(plaintext: Vec<u8>) -> Result<Self, KeyringError> {
+        Ok(plaintext)
+    }
+}
+
+impl Plaintext for String {
+    fn into_plaintext(self) -> Vec<u8> {
+        self.into_bytes()
+    }
+
+    fn from_plaintext(plaintext: Vec<u8>) -> Result<Self, KeyringError> {
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+static KEYRING: RwLock<Option<Box<dyn Keyring>>> = RwLock::new(None);
+
+pub fn set_keyring<K: Keyring + 'static>(keyring: K) {
+    *KEYRING.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(keyring));
+}
+
+pub fn clear_keyring() {
+    *KEYRING.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+#[doc(hidden)]
+pub fn seal<T: Plaintext>(key: &str, value: T) -> Result<Vec<u8>, Error> {
+    let keyring = KEYRING.read().unwrap_or_else(|e| e.into_inner());
+    let keyring = keyring.as_ref().ok_or_else(|| Error::new_no_keyring(key))?;
+    keyring.encrypt(key, &value.into_plaintext()).map_err(|e| Error::new_keyring_error(key, e))
+}
+
+#[doc(hidden)]
+pub fn open<T: Plaintext>(key: &str, ciphertext: Vec<u8>) -> Result<T, Error> {
+    // an unset field, e.g. of a message encoded without the field
+    if ciphertext.is_empty() {
+        return Ok(T::default());
+    }
+    let keyring = KEYRING.read().unwrap_or_else(|e| e.into_inner());
+    let keyring = keyring.as_ref().ok_or_else(|| Error::new_no_keyring(key))?;
+    let plaintext =
+        keyring.decrypt(key, &ciphertext).map_err(|e| Error::new_keyring_error(key, e))?;
+    T::from_plaintext(plaintext).map_err(|e| Error::new_keyring_error(key, e))
+}
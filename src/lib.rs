@@ -2,23 +2,50 @@
 extern crate log;
 extern crate protobuf_gen_extract as extract;
 
+pub mod anonymize;
+#[doc(hidden)]
+pub mod backend;
+pub mod conformance;
+pub mod corpus;
+pub mod descriptor;
 pub mod error;
+pub mod ffi;
+pub mod keyring;
+pub mod metrics;
+pub mod observer;
 pub mod parse;
+pub mod plugin;
 pub mod print;
-mod types;
+pub mod proto_default;
+pub mod replay;
+mod rng;
+pub mod status;
+pub mod store;
+#[doc(hidden)]
+pub mod types;
+pub mod version;
+pub mod wire_struct;
 
-use std::collections::HashMap;
-use std::fs::{self, File};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
 use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::result;
 
 use thiserror::Error;
 
+use crate::backend::{Backend, ProtobufBackend, Schema};
 use crate::parse::SchemaFile;
-use crate::print::SchemaPrinter;
+use crate::settings::IntEncoding;
 use crate::types::FieldType;
+use crate::version::SchemaLock;
 pub use error::Error;
+pub use extract::diagnostic;
+pub use extract::settings::{self, Settings};
+pub use keyring::Keyring;
+pub use observer::{ConversionObserver, Direction};
+pub use proto_default::ProtoDefault;
 pub use protobuf_gen_derive::*;
 
 pub trait ProtobufGen: Sized {
@@ -33,6 +60,11 @@ pub struct Config {
     pub proxy_target_dir: Option<PathBuf>,
     pub sources: HashMap<String, Vec<PathBuf>>,
     pub type_replacement: HashMap<String, String>,
+    pub schema_lock: Option<PathBuf>,
+    pub ffi_header: Option<PathBuf>,
+    pub descriptor_set: Option<PathBuf>,
+    pub settings: Option<Settings>,
+    backend: Box<dyn Backend>,
 }
 
 #[derive(Error, Debug)]
@@ -41,6 +73,16 @@ pub enum ConfigError {
     IoError(#[from] io::Error),
     #[error("failed to parse a string.")]
     ParseError(#[from] syn::Error),
+    #[error("schema of `{0}` has changed, but its schema_version is not bumped from {1}.")]
+    SchemaVersionNotBumped(String, u32),
+    #[error("schema_version of `{0}` is decreased from {1} to {2}.")]
+    SchemaVersionDecreased(String, u32, u32),
+    #[error("schema_version of `{0}` must fit in u32, but is {1}.")]
+    InvalidSchemaVersion(String, u64),
+    #[error("failed to read settings.")]
+    SettingsError(#[from] settings::SettingsError),
+    #[error("`{0}` is declared in both packages `{1}` and `{2}`.")]
+    ConflictingPackages(String, String, String),
 }
 
 impl Config {
@@ -53,6 +95,11 @@ impl Config {
             proxy_target_dir: proxy_target_dir.map(|p| p.into()),
             sources: HashMap::new(),
             type_replacement: HashMap::new(),
+            schema_lock: None,
+            ffi_header: None,
+            descriptor_set: None,
+            settings: None,
+            backend: Box::new(ProtobufBackend),
         }
     }
 
@@ -60,93 +107,216 @@ impl Config {
         self.type_replacement.insert(old, new);
     }
 
-    pub fn add_source<P: Into<PathBuf>, S: Into<String>>(&mut self, file: P, package: S) {
-        self.sources.entry(package.into()).or_default().push(file.into());
+    /// Fails the generation if a type with `schema_version` has a changed schema but the same
+    /// version as recorded in `lock_file`. The lock file is updated after every generation.
+    pub fn check_schema_versions<P: Into<PathBuf>>(&mut self, lock_file: P) {
+        self.schema_lock = Some(lock_file.into());
     }
 
-    fn create_proto_file<P: AsRef<str>>(&self, package: P) -> io::Result<(File, PathBuf)> {
-        let package: Vec<_> = package.as_ref().split('.').collect();
-        let (dir, file) = package.split_at(package.len() - 1);
+    /// Writes the C header declaring the functions exported for `#[protobuf_gen(ffi)]` types.
+    pub fn ffi_header<P: Into<PathBuf>>(&mut self, path: P) {
+        self.ffi_header = Some(path.into());
+    }
 
-        let mut dir_path = self.proto_target_dir.clone();
-        dir_path.extend(dir);
+    /// Writes the `FileDescriptorSet` of the generated schemas, including their imports, to `path`
+    /// for tools driven by descriptors, e.g. `corpus::Corpus`.
+    pub fn descriptor_set<P: Into<PathBuf>>(&mut self, path: P) {
+        self.descriptor_set = Some(path.into());
+    }
 
-        let mut file_path = dir_path.as_path().join(PathBuf::from(file[0].to_string()));
-        file_path.set_extension("proto");
+    /// Uses `settings` instead of reading `protobuf-gen.conf` of the crate being built, which the
+    /// derive reads as well.
+    pub fn settings(&mut self, settings: Settings) {
+        self.settings = Some(settings);
+    }
 
-        fs::create_dir_all(dir_path)?;
-        Ok((File::create(file_path.as_path())?, file_path))
+    /// Experimental: writes the output of `backend` instead of `.proto` files and proxies, see
+    /// `backend`.
+    #[doc(hidden)]
+    pub fn backend<B: Backend + 'static>(&mut self, backend: B) {
+        self.backend = Box::new(backend);
+    }
+
+    pub fn add_source<P: Into<PathBuf>, S: Into<String>>(&mut self, file: P, package: S) {
+        self.sources.entry(package.into()).or_default().push(file.into());
     }
 
     fn build_context(&self) -> result::Result<Context, ConfigError> {
-        let mut context = Context::default();
+        let settings = match self.settings {
+            Some(ref settings) => settings.clone(),
+            None => Settings::from_manifest_dir()?,
+        };
+        let mut context = Context::new(settings);
         for (old, new) in &self.type_replacement {
             context.add_type_replacement(old.to_string(), new.to_string());
         }
 
         // generate item dictionary
         for (package, sources) in &self.sources {
+            let package = context.settings.package(package);
             for source in sources {
                 let file: syn::File = syn::parse_str(&fs::read_to_string(source)?)?;
-                context.item_dictionary.collect(&file.items, package);
+                context.item_dictionary.collect(&file.items, &package)?;
             }
         }
         Ok(context)
     }
 
     pub fn generate(&self) -> result::Result<(), ConfigError> {
-        let mut in_files = Vec::new();
         let mut context = self.build_context()?;
+        let mut schema_lock = match self.schema_lock {
+            Some(ref path) => Some(SchemaLock::load(path)?),
+            None => None,
+        };
+        let mut ffi_header = ffi::HeaderBuilder::default();
+        let mut file_packages = BTreeMap::new();
+        let mut schemas = Vec::new();
 
         // generate protobuf schemas from Rust
         for (package, sources) in &self.sources {
+            let package = &context.settings.package(package);
             context.current_package = package.clone();
+            ffi_header.package = package.clone();
 
-            let mut schema_file = SchemaFile::default();
-            schema_file.package = package.clone();
+            // types without `#[protobuf_gen(file = "..")]` land in the file named after the package
+            let mut schema_files = BTreeMap::new();
+            schema_files.insert(proto_path(package), SchemaFile::default());
             for source in sources {
                 debug!("processing {} in {}", source.display(), package);
                 let syn_file: syn::File = syn::parse_str(&fs::read_to_string(source)?)?;
-                schema_file.merge(&mut parse::build_schema_file(&context, &syn_file));
+                for (path, mut other) in parse::build_schema_files(&context, &syn_file)? {
+                    schema_files.entry(path).or_default().merge(&mut other);
+                }
+                extract::extract_from_file(&mut ffi_header, &syn_file)?;
             }
 
-            let (mut file, file_path) = self.create_proto_file(package)?;
-            write!(file, "{}", SchemaPrinter(&schema_file))?;
+            for (path, mut schema_file) in schema_files {
+                if let Some(other) = file_packages.insert(path.clone(), package.clone()) {
+                    return Err(ConfigError::ConflictingPackages(
+                        path.display().to_string(),
+                        other,
+                        package.clone(),
+                    ));
+                }
+                schema_file.package = package.clone();
 
-            in_files.push(file_path);
+                if let Some(ref mut schema_lock) = schema_lock {
+                    schema_lock.update(&schema_file)?;
+                }
+                schemas.push(Schema { path, file: schema_file });
+            }
         }
 
-        // generate Rust bindings for protobuf
-        if let Some(ref proxy_target_dir) = self.proxy_target_dir {
-            fs::create_dir_all(proxy_target_dir)?;
+        if let Some(ref path) = self.ffi_header {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, ffi_header.build())?;
+        }
 
-            let mut config = prost_build::Config::new();
-            config.type_attribute(".", "#[allow(clippy::large_enum_variant)]");
-            config.out_dir(proxy_target_dir);
-            config.compile_protos(&in_files, &[PathBuf::from(&self.proto_target_dir)])?;
+        // a build script shares its `OUT_DIR` with the derive compiling the crate
+        if let Some(out_dir) = std::env::var_os("OUT_DIR") {
+            let mut index = extract::index::TypeIndex::default();
+            for schema in &schemas {
+                let names = schema.file.messages.iter().map(|m| &m.name);
+                for name in names.chain(schema.file.enums.iter().map(|e| &e.name)) {
+                    let package = &schema.file.package;
+                    let ty = extract::index::ProtoType {
+                        package: package.clone(),
+                        path: schema.path.clone(),
+                        inherent: context.item_dictionary.is_inherent(package, name),
+                    };
+                    index.insert(name, ty);
+                }
+            }
+            fs::write(Path::new(&out_dir).join(extract::index::INDEX_FILE), index.to_string())?;
+        }
+
+        self.backend.generate(self, &context.settings, &schemas)?;
+
+        if let (Some(path), Some(schema_lock)) = (&self.schema_lock, &schema_lock) {
+            schema_lock.save(path)?;
         }
         Ok(())
     }
 }
 
+/// Returns the path of the `.proto` file named after `package`, e.g. `a/b/c.proto` for `a.b.c`.
+pub(crate) fn proto_path(package: &str) -> PathBuf {
+    PathBuf::from(package.replace('.', "/")).with_extension("proto")
+}
+
 #[derive(Debug, Default)]
 pub struct ItemDictionary {
     package_map: HashMap<String, String>,
+    file_map: HashMap<String, PathBuf>,
+    // the `some` types of `#[protobuf_gen(optional)]` enums, which have no schemas of their own.
+    optional_map: HashMap<String, syn::Type>,
+    // the packages and names of the messages with the inherent conversions only, for the index.
+    inherent_set: HashSet<(String, String)>,
 }
 
 impl ItemDictionary {
-    pub fn collect(&mut self, items: &[syn::Item], package: &str) {
+    pub fn collect(&mut self, items: &[syn::Item], package: &str) -> syn::Result<()> {
         for item in items {
-            match item {
-                syn::Item::Struct(inner) => {
-                    self.package_map.insert(inner.ident.to_string(), package.to_string());
+            let item = match extract::Conversions::from_item_in(item, items) {
+                Some(conversions) => Cow::Owned(conversions.to_item()),
+                None => Cow::Borrowed(item),
+            };
+            if let syn::Item::Enum(item_enum) = &*item {
+                if let Some(optional_enum) = extract::OptionalEnum::new(item_enum) {
+                    self.optional_map.insert(item_enum.ident.to_string(), optional_enum.ty.clone());
+                    continue;
                 }
-                syn::Item::Enum(inner) => {
-                    self.package_map.insert(inner.ident.to_string(), package.to_string());
+            }
+            match &*item {
+                syn::Item::Struct(syn::ItemStruct { ident, attrs, .. })
+                | syn::Item::Enum(syn::ItemEnum { ident, attrs, .. }) => {
+                    // the file is joined to the target directory
+                    extract::diagnostic::check_file(attrs)?;
+                    let file =
+                        syn_util::get_attribute_value::<String>(attrs, &["protobuf_gen", "file"])
+                            .map_or_else(|| proto_path(package), PathBuf::from);
+                    self.package_map.insert(ident.to_string(), package.to_string());
+                    self.file_map.insert(ident.to_string(), file);
+                    // enumerators convert from and into `i32` either way
+                    let is_message = match &*item {
+                        syn::Item::Enum(item_enum) => item_enum
+                            .variants
+                            .iter()
+                            .any(|v| !matches!(v.fields, syn::Fields::Unit)),
+                        _ => true,
+                    };
+                    if is_message
+                        && syn_util::contains_attribute(attrs, &["protobuf_gen", "inherent"])
+                        && !syn_util::contains_attribute(
+                            attrs,
+                            &["protobuf_gen", "keep_trait_impls"],
+                        )
+                    {
+                        self.inherent_set.insert((package.to_string(), ident.to_string()));
+                    }
                 }
                 _ => {}
             }
         }
+        Ok(())
+    }
+
+    /// Returns the path of the `.proto` file which defines the type `ident`.
+    pub(crate) fn file_of(&self, ident: &str) -> Option<&Path> {
+        self.file_map.get(ident).map(PathBuf::as_path)
+    }
+
+    /// Returns whether the type `ident` in `package` has `#[protobuf_gen(inherent)]` without
+    /// `keep_trait_impls`.
+    pub(crate) fn is_inherent(&self, package: &str, ident: &str) -> bool {
+        self.inherent_set.contains(&(package.to_string(), ident.to_string()))
+    }
+
+    /// Returns the `some` type of `ident` if it is a `#[protobuf_gen(optional)]` enum.
+    pub(crate) fn optional_of(&self, ident: &str) -> Option<&syn::Type> {
+        self.optional_map.get(ident)
     }
 }
 
@@ -155,6 +325,7 @@ pub struct Context {
     current_package: String,
     type_replacement: HashMap<String, FieldType>,
     item_dictionary: ItemDictionary,
+    settings: Settings,
 }
 
 impl Default for Context {
@@ -180,11 +351,36 @@ impl Default for Context {
             .collect(),
             current_package: Default::default(),
             item_dictionary: Default::default(),
+            settings: Default::default(),
         }
     }
 }
 
 impl Context {
+    fn new(settings: Settings) -> Self {
+        let mut context = Self::default();
+        let encodings: &[(&str, FieldType)] = match settings.default_int_encoding {
+            IntEncoding::Sint => &[],
+            IntEncoding::Int => &[("i32", FieldType::Int32), ("i64", FieldType::Int64)],
+            IntEncoding::Fixed => &[
+                ("i8", FieldType::Sfixed32),
+                ("i16", FieldType::Sfixed32),
+                ("i32", FieldType::Sfixed32),
+                ("i64", FieldType::Sfixed64),
+                ("u8", FieldType::Fixed32),
+                ("u16", FieldType::Fixed32),
+                ("u32", FieldType::Fixed32),
+                ("u64", FieldType::Fixed64),
+                ("usize", FieldType::Fixed64),
+            ],
+        };
+        for (ty, field_type) in encodings {
+            context.type_replacement.insert(ty.to_string(), field_type.clone());
+        }
+        context.settings = settings;
+        context
+    }
+
     pub fn add_type_replacement(&mut self, old: String, new: String) {
         self.type_replacement.insert(old, FieldType::MessageOrEnum(new));
     }
@@ -0,0 +1,191 @@
+//! Measures how many bytes each field contributes to encoded messages, to find the fields which
+//! dominate bandwidth.
+//!
+//! A field is identified by its path from the root message, e.g. `Person.job.designer.name`, so
+//! the bytes of a nested field count towards every message field enclosing it. Fields unknown to
+//! the descriptors are written as `#<number>`. The bytes of a field include its keys and lengths.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+
+use prost::encoding::{decode_key, WireType};
+use prost_types::field_descriptor_proto::Type;
+
+use crate::descriptor::{DescriptorError, Descriptors};
+use crate::replay::{frames, take_value};
+
+/// The sizes of a field over the messages in which it is present.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FieldSize {
+    /// The number of messages in which the field is present.
+    pub messages: u64,
+    /// The bytes of the field summed over all messages.
+    pub bytes: u64,
+    /// The largest number of bytes of the field in a message.
+    pub max: u64,
+    // `histogram[i]` counts the messages in which the field has `2^(i-1)..2^i` bytes.
+    histogram: Vec<u64>,
+}
+
+impl FieldSize {
+    fn record(&mut self, bytes: u64) {
+        self.messages += 1;
+        self.bytes += bytes;
+        self.max = self.max.max(bytes);
+
+        let bucket = (64 - bytes.leading_zeros()) as usize;
+        if self.histogram.len() <= bucket {
+            self.histogram.resize(bucket + 1, 0);
+        }
+        self.histogram[bucket] += 1;
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.messages == 0 {
+            0.0
+        } else {
+            self.bytes as f64 / self.messages as f64
+        }
+    }
+
+    /// Returns the number of messages by ranges of bytes, which double in size, skipping empty
+    /// ranges.
+    pub fn histogram(&self) -> Vec<(Range<u64>, u64)> {
+        self.histogram
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(i, &count)| match i {
+                0 => (0..1, count),
+                _ => (1 << (i - 1)..1 << i, count),
+            })
+            .collect()
+    }
+}
+
+pub struct SizeMetrics<'a> {
+    descriptors: &'a Descriptors,
+    message: String,
+    messages: u64,
+    bytes: u64,
+    fields: BTreeMap<String, FieldSize>,
+}
+
+impl<'a> SizeMetrics<'a> {
+    /// Measures encodings of `message`, e.g. `yellow_book.Person`.
+    pub fn new(descriptors: &'a Descriptors, message: &str) -> Result<Self, DescriptorError> {
+        descriptors.message(message)?;
+        Ok(Self {
+            descriptors,
+            message: message.trim_start_matches('.').to_string(),
+            messages: 0,
+            bytes: 0,
+            fields: BTreeMap::new(),
+        })
+    }
+
+    pub fn add(&mut self, encoded: &[u8]) -> Result<(), DescriptorError> {
+        let name = self.message.rsplit('.').next().unwrap_or(&self.message);
+        let mut sizes = BTreeMap::new();
+        scan(self.descriptors, &self.message, name, encoded, &mut sizes)?;
+
+        self.messages += 1;
+        self.bytes += encoded.len() as u64;
+        for (path, bytes) in sizes {
+            self.fields.entry(path).or_default().record(bytes);
+        }
+        Ok(())
+    }
+
+    /// Adds every message of `corpus`, a sequence of length-delimited messages.
+    pub fn add_corpus(&mut self, corpus: &[u8]) -> Result<(), DescriptorError> {
+        for frame in frames(corpus)? {
+            self.add(frame)?;
+        }
+        Ok(())
+    }
+
+    pub fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn field(&self, path: &str) -> Option<&FieldSize> {
+        self.fields.get(path)
+    }
+
+    /// Returns the fields by their paths, the ones with the most bytes first.
+    pub fn fields(&self) -> Vec<(&str, &FieldSize)> {
+        let mut fields: Vec<_> =
+            self.fields.iter().map(|(path, size)| (path.as_str(), size)).collect();
+        fields.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then(a.0.cmp(b.0)));
+        fields
+    }
+}
+
+impl<'a> fmt::Display for SizeMetrics<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: {} messages, {} bytes", self.message, self.messages, self.bytes)?;
+        writeln!(
+            f,
+            "{:<40} {:>8} {:>12} {:>7} {:>10} {:>8}  histogram",
+            "field", "messages", "bytes", "share", "mean", "max"
+        )?;
+        for (path, size) in self.fields() {
+            let share =
+                if self.bytes == 0 { 0.0 } else { size.bytes as f64 * 100.0 / self.bytes as f64 };
+            let histogram: Vec<_> = size
+                .histogram()
+                .into_iter()
+                .map(|(range, count)| format!("{}..{}:{}", range.start, range.end, count))
+                .collect();
+            writeln!(
+                f,
+                "{:<40} {:>8} {:>12} {:>6.1}% {:>10.1} {:>8}  {}",
+                path,
+                size.messages,
+                size.bytes,
+                share,
+                size.mean(),
+                size.max,
+                histogram.join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Adds the bytes of every field of `bytes`, an encoding of `message`, to `sizes` by their paths.
+fn scan(
+    descriptors: &Descriptors,
+    message: &str,
+    path: &str,
+    mut bytes: &[u8],
+    sizes: &mut BTreeMap<String, u64>,
+) -> Result<(), DescriptorError> {
+    let message = descriptors.message(message)?;
+    while !bytes.is_empty() {
+        let start = bytes;
+        let (tag, wire_type) =
+            decode_key(&mut bytes).map_err(|e| DescriptorError::MalformedMessage(e.to_string()))?;
+        let value = take_value(wire_type, &mut bytes).map_err(DescriptorError::MalformedMessage)?;
+        let len = (start.len() - bytes.len()) as u64;
+
+        let field = message.field.iter().find(|field| field.number() as u32 == tag);
+        let field_path = match field {
+            Some(field) => format!("{}.{}", path, field.name()),
+            None => format!("{}.#{}", path, tag),
+        };
+        if let Some(field) = field {
+            if field.r#type() == Type::Message && wire_type == WireType::LengthDelimited {
+                scan(descriptors, field.type_name(), &field_path, value, sizes)?;
+            }
+        }
+        *sizes.entry(field_path).or_default() += len;
+    }
+    Ok(())
+}
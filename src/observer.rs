@@ -0,0 +1,43 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Encode,
+    Decode,
+}
+
+/// Receives a notification for every `to_protobuf`/`from_protobuf` call made by generated code.
+///
+/// `size` is the number of bytes written or read, which is `0` if the conversion failed before
+/// reaching the wire. The observer is called without holding any lock, so it may set or clear
+/// the observer itself.
+pub trait ConversionObserver: Send + Sync {
+    fn observe(&self, message: &'static str, direction: Direction, size: usize, success: bool);
+}
+
+static OBSERVER: RwLock<Option<Arc<dyn ConversionObserver>>> = RwLock::new(None);
+// whether `OBSERVER` is set, so that conversions skip the lock if no observer is.
+static HAS_OBSERVER: AtomicBool = AtomicBool::new(false);
+
+pub fn set_observer<O: ConversionObserver + 'static>(observer: O) {
+    *OBSERVER.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(observer));
+    HAS_OBSERVER.store(true, Ordering::Release);
+}
+
+pub fn clear_observer() {
+    let mut observer = OBSERVER.write().unwrap_or_else(|e| e.into_inner());
+    HAS_OBSERVER.store(false, Ordering::Release);
+    *observer = None;
+}
+
+#[doc(hidden)]
+pub fn notify(message: &'static str, direction: Direction, size: usize, success: bool) {
+    if !HAS_OBSERVER.load(Ordering::Acquire) {
+        return;
+    }
+    let observer = OBSERVER.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(observer) = observer {
+        observer.observe(message, direction, size, success);
+    }
+}
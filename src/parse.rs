@@ -1,22 +1,28 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
 use std::ops::{Deref, DerefMut};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::types::{
     Enumerator, Field, FieldType, FileDescriptor, Frequency, Message, OneOf, Syntax,
 };
+use syn::spanned::Spanned;
 use syn::visit::{self, Visit};
 use syn::{
-    self, Fields, FieldsNamed, File, GenericArgument, Ident, ItemEnum, ItemStruct, PathArguments,
-    Type, TypePath, Variant,
+    self, Attribute, Fields, FieldsNamed, File, GenericArgument, Ident, Item, ItemEnum, ItemStruct,
+    Lit, PathArguments, Type, TypePath, Variant,
 };
 
-use super::Context;
+use super::{ConfigError, Context};
+use crate::diagnostic::Diagnostic;
+use crate::extract::settings::OptionalStyle;
 use crate::extract::{self, Extract};
+use crate::version;
 
 struct RequiredImportsCollector<'a> {
     context: &'a Context,
-    imports: BTreeSet<String>,
+    current_file: &'a Path,
+    imports: BTreeSet<PathBuf>,
 }
 
 impl<'a> Extract for RequiredImportsCollector<'a> {
@@ -39,31 +45,50 @@ impl<'a> Extract for RequiredImportsCollector<'a> {
 
 impl<'a, 'ast> Visit<'ast> for RequiredImportsCollector<'a> {
     fn visit_type_path(&mut self, type_path: &TypePath) {
-        let ident = type_path_ident(type_path);
+        self.collect_import(type_path_ident(type_path));
+        visit::visit_type_path(self, type_path);
+    }
+}
+
+impl<'a> RequiredImportsCollector<'a> {
+    fn collect_import(&mut self, ident: &Ident) {
+        // a field of an optional enum refers to its `some` type.
+        if let Some(Type::Path(type_path)) =
+            self.context.item_dictionary.optional_of(&ident.to_string())
+        {
+            return self.collect_import(type_path_ident(type_path));
+        }
+
         if !self.context.type_replacement.contains_key(ident.to_string().as_str()) {
-            if let Some(package) = self
+            if let Some(file) = self
                 .context
                 .item_dictionary
-                .package_map
-                .get(&ident.to_string())
-                .filter(|&package| package != &self.context.current_package)
+                .file_of(&ident.to_string())
+                .filter(|&file| file != self.current_file)
             {
-                self.imports.insert(package.to_string());
+                self.imports.insert(file.to_path_buf());
             }
         }
-        visit::visit_type_path(self, type_path);
     }
 }
 
-pub fn collect_required_imports<'a>(context: &'a Context, file: &File) -> BTreeSet<String> {
-    let mut collector = RequiredImportsCollector { context, imports: Default::default() };
-    extract::extract_from_file(&mut collector, file);
-    collector.imports
+/// Returns the `.proto` files to import into `current_file` for the types in `file`.
+pub fn collect_required_imports<'a>(
+    context: &'a Context,
+    file: &File,
+    current_file: &'a Path,
+) -> syn::Result<BTreeSet<PathBuf>> {
+    let mut collector =
+        RequiredImportsCollector { context, current_file, imports: Default::default() };
+    extract::extract_from_file(&mut collector, file)?;
+    Ok(collector.imports)
 }
 
 struct SchemaFileBuilder<'a> {
     context: &'a Context,
     file_descriptor: FileDescriptor,
+    // the first error, since the callbacks cannot return one.
+    error: Option<ConfigError>,
 }
 
 impl<'a> Extract for SchemaFileBuilder<'a> {
@@ -78,9 +103,16 @@ impl<'a> Extract for SchemaFileBuilder<'a> {
             .enumerate()
             .map(|(i, field)| self.field_to_schema(field, i + 1))
             .collect();
+        let schema_version = self.schema_version(&item_struct.ident, &item_struct.attrs);
+        let unique_by = syn_util::get_attribute_value::<String>(
+            &item_struct.attrs,
+            &["protobuf_gen", "unique_by"],
+        );
         self.add_message(Message {
             name: item_struct.ident.to_string(),
             fields,
+            schema_version,
+            unique_by,
             ..Message::default()
         });
     }
@@ -111,6 +143,7 @@ impl<'a> Extract for SchemaFileBuilder<'a> {
     }
 
     fn extract_one_of(&mut self, item_enum: &ItemEnum) {
+        let status = self.check_status(item_enum);
         let fields: Vec<_> = item_enum
             .variants
             .iter()
@@ -119,11 +152,27 @@ impl<'a> Extract for SchemaFileBuilder<'a> {
                 let i = i + 1;
                 if let Fields::Unnamed(fields_unnamed) = &variant.fields {
                     let mut field = fields_unnamed.unnamed.clone().pop().unwrap().into_value();
+                    if let Type::Path(type_path) = &field.ty {
+                        let ident = type_path_ident(type_path);
+                        if self.context.item_dictionary.optional_of(&ident.to_string()).is_some() {
+                            self.error.get_or_insert(ConfigError::ParseError(
+                                Diagnostic::InvalidAttribute.error(
+                                    type_path.span(),
+                                    format!(
+                                        "`{}` is `optional`, which is not supported in a `oneof`",
+                                        ident
+                                    ),
+                                ),
+                            ));
+                        }
+                    }
                     field.ident = Some(variant.ident.clone());
-                    self.field_to_schema(&field, i)
+                    let mut field = self.field_to_schema(&field, i);
+                    field.name = self.context.settings.one_of_field_name(&field.name);
+                    field
                 } else {
                     Field {
-                        name: variant.ident.to_string(),
+                        name: self.context.settings.one_of_field_name(&variant.ident.to_string()),
                         typ: FieldType::MessageOrEnum(format!("{}Inner", variant.ident)),
                         number: i as i32,
                         frequency: Frequency::Required,
@@ -131,28 +180,38 @@ impl<'a> Extract for SchemaFileBuilder<'a> {
                         packed: None,
                         boxed: false,
                         deprecated: false,
+                        sensitive: false,
                     }
                 }
             })
             .collect();
 
+        let schema_version = self.schema_version(&item_enum.ident, &item_enum.attrs);
         self.add_message(Message {
             name: item_enum.ident.to_string(),
             oneofs: vec![OneOf { name: "inner".to_string(), fields, ..OneOf::default() }],
+            schema_version,
+            status,
             ..Message::default()
         });
     }
 
     fn extract_enumerator(&mut self, item_enum: &ItemEnum) {
+        let item = item_enum.ident.to_string();
         let fields = item_enum
             .variants
             .iter()
             .enumerate()
-            .map(|(i, variant)| (variant.ident.to_string(), i as i32))
+            .map(|(i, variant)| {
+                let name = self.context.settings.enum_value_name(&item, &variant.ident.to_string());
+                (name, i as i32)
+            })
             .collect();
+        let schema_version = self.schema_version(&item_enum.ident, &item_enum.attrs);
         self.add_enum(Enumerator {
             name: item_enum.ident.to_string(),
             fields,
+            schema_version,
             ..Enumerator::default()
         });
     }
@@ -180,13 +239,15 @@ pub(crate) fn generic_type_of(type_path: &TypePath) -> Option<&Type> {
     })
 }
 
-fn type_frequency(typ: &Type) -> Frequency {
+fn type_frequency(typ: &Type, optional_style: OptionalStyle) -> Frequency {
     match typ {
         Type::Array(_) => Frequency::Repeated,
         Type::Path(type_path) => {
             let ident = type_path_ident(&type_path);
             if ident == "Vec" || ident == "HashSet" {
                 Frequency::Repeated
+            } else if ident == "Option" && optional_style == OptionalStyle::Proto3 {
+                Frequency::Optional
             } else {
                 Frequency::Required
             }
@@ -196,6 +257,45 @@ fn type_frequency(typ: &Type) -> Frequency {
 }
 
 impl<'a> SchemaFileBuilder<'a> {
+    fn schema_version(&mut self, ident: &Ident, attrs: &[Attribute]) -> Option<u32> {
+        let version =
+            syn_util::get_attribute_value::<u64>(attrs, &["protobuf_gen", "schema_version"])?;
+        match u32::try_from(version) {
+            Ok(version) => Some(version),
+            Err(_) => {
+                self.error.get_or_insert(ConfigError::InvalidSchemaVersion(
+                    format!("{}.{}", self.context.current_package, ident),
+                    version,
+                ));
+                None
+            }
+        }
+    }
+
+    // Returns whether `item_enum` is converted to `Status`, whose details are typed by the protobuf
+    // name of the error, which the derive is given since it does not know the package.
+    fn check_status(&mut self, item_enum: &ItemEnum) -> bool {
+        if let Some(Lit::Str(status)) =
+            syn_util::get_attribute_value::<Lit>(&item_enum.attrs, &["protobuf_gen", "status"])
+        {
+            let name = format!("{}.{}", self.context.current_package, item_enum.ident);
+            if status.value() != name {
+                self.error.get_or_insert(ConfigError::ParseError(
+                    Diagnostic::InvalidAttribute.error(
+                        status.span(),
+                        format!(
+                            "expected `status = \"{}\"`, the protobuf name of `{}`",
+                            name, item_enum.ident
+                        ),
+                    ),
+                ));
+            }
+            true
+        } else {
+            false
+        }
+    }
+
     fn add_message(&mut self, m: Message) {
         debug!("Message {}", m.name);
         self.file_descriptor.messages.push(m);
@@ -224,7 +324,7 @@ impl<'a> SchemaFileBuilder<'a> {
                 let ident = type_path_ident(&type_path);
                 if let Some(ty) = self.context.type_replacement.get(ident.to_string().as_str()) {
                     ty.clone()
-                } else if ident == "Vec" || ident == "HashSet" {
+                } else if ident == "Vec" || ident == "HashSet" || ident == "Option" {
                     self.type_field_type(generic_type_of(&type_path).unwrap())
                 } else if let Some(package) = self
                     .context
@@ -242,7 +342,41 @@ impl<'a> SchemaFileBuilder<'a> {
         }
     }
 
-    fn field_to_schema(&self, field: &syn::Field, number: usize) -> Field {
+    // Returns a `#[protobuf_gen(optional)]` enum in the generic arguments of `ty` or as the element
+    // of an array, e.g. `T` of `Vec<T>`, which has no schema to convert to.
+    fn nested_optional_enum<'t>(&self, ty: &'t Type) -> Option<&'t TypePath> {
+        let nested: Vec<&Type> = match ty {
+            Type::Array(array) => vec![&*array.elem],
+            Type::Path(type_path) => type_path
+                .path
+                .segments
+                .iter()
+                .flat_map(|segment| match &segment.arguments {
+                    PathArguments::AngleBracketed(arguments) => arguments.args.iter().collect(),
+                    _ => Vec::new(),
+                })
+                .filter_map(|argument| match argument {
+                    GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        nested.into_iter().find_map(|ty| match ty {
+            Type::Path(type_path)
+                if self
+                    .context
+                    .item_dictionary
+                    .optional_of(&type_path_ident(type_path).to_string())
+                    .is_some() =>
+            {
+                Some(type_path)
+            }
+            ty => self.nested_optional_enum(ty),
+        })
+    }
+
+    fn field_to_schema(&mut self, field: &syn::Field, number: usize) -> Field {
         if let Some(substitute) =
             syn_util::get_attribute_value::<String>(&field.attrs, &["protobuf_gen", "substitute"])
         {
@@ -255,36 +389,138 @@ impl<'a> SchemaFileBuilder<'a> {
                 packed: None,
                 boxed: false,
                 deprecated: false,
+                sensitive: false,
+            };
+        }
+
+        if syn_util::get_attribute_value::<String>(&field.attrs, &["protobuf_gen", "encrypt"])
+            .is_some()
+        {
+            return Field {
+                name: field.ident.as_ref().unwrap().to_string(),
+                frequency: Frequency::Required,
+                typ: FieldType::Bytes_,
+                number: number as i32,
+                default: None,
+                packed: None,
+                boxed: false,
+                deprecated: false,
+                sensitive: false,
             };
         }
 
+        if let Some(type_path) = self.nested_optional_enum(&field.ty) {
+            self.error.get_or_insert(ConfigError::ParseError(Diagnostic::InvalidAttribute.error(
+                type_path.span(),
+                format!(
+                    "`{}` is `optional`, which is only supported as the type of a field",
+                    type_path_ident(type_path)
+                ),
+            )));
+        }
+
+        // a field of an optional enum is an `optional` field of its `some` type, whatever the
+        // `optional_style` is.
+        let (ty, frequency) = match &field.ty {
+            Type::Path(type_path) => {
+                match self
+                    .context
+                    .item_dictionary
+                    .optional_of(&type_path_ident(type_path).to_string())
+                {
+                    Some(some) => (some.clone(), Frequency::Optional),
+                    None => (
+                        field.ty.clone(),
+                        type_frequency(&field.ty, self.context.settings.optional_style),
+                    ),
+                }
+            }
+            _ => {
+                (field.ty.clone(), type_frequency(&field.ty, self.context.settings.optional_style))
+            }
+        };
+
         Field {
             name: field.ident.as_ref().unwrap().to_string(),
-            frequency: type_frequency(&field.ty),
-            typ: self.type_field_type(&field.ty),
+            frequency,
+            typ: self.type_field_type(&ty),
             number: number as i32,
             default: None,
             packed: None,
             boxed: false,
             deprecated: false,
+            sensitive: syn_util::contains_attribute(&field.attrs, &["protobuf_gen", "sensitive"]),
+        }
+    }
+}
+
+/// Builds the schemas of the types in `file`, grouped by the `.proto` files they land in, i.e.
+/// `#[protobuf_gen(file = "..")]` or the file named after the current package. Types are kept in
+/// their declaration order in each group.
+pub fn build_schema_files(
+    context: &Context,
+    file: &File,
+) -> Result<BTreeMap<PathBuf, SchemaFile>, ConfigError> {
+    let default_file = crate::proto_path(&context.current_package);
+
+    let mut groups: BTreeMap<PathBuf, Vec<Item>> = BTreeMap::new();
+    // every group keeps the `use` declarations, which may import `conversions!`
+    let uses: Vec<_> = file.items.iter().filter(|item| matches!(item, Item::Use(_))).collect();
+    for item in &file.items {
+        if let Item::Use(_) = item {
+            continue;
         }
+        let item_file = match extract::Conversions::from_item_in(item, &file.items) {
+            Some(conversions) => context.item_dictionary.file_of(&conversions.ident.to_string()),
+            None => match item {
+                Item::Struct(ItemStruct { ident, .. }) | Item::Enum(ItemEnum { ident, .. }) => {
+                    context.item_dictionary.file_of(&ident.to_string())
+                }
+                _ => None,
+            },
+        };
+        let item_file = item_file.unwrap_or(&default_file);
+        groups
+            .entry(item_file.to_path_buf())
+            .or_insert_with(|| uses.iter().map(|item| (*item).clone()).collect())
+            .push(item.clone());
     }
+
+    groups
+        .into_iter()
+        .map(|(path, items)| {
+            let group = File { shebang: file.shebang.clone(), attrs: file.attrs.clone(), items };
+            let schema_file = build_schema_file(context, &group, &path)?;
+            Ok((path, schema_file))
+        })
+        .collect()
 }
 
-pub fn build_schema_file<'a>(context: &'a Context, file: &File) -> SchemaFile {
+/// Builds the schema of the types in `file`, which land in `current_file`.
+pub fn build_schema_file<'a>(
+    context: &'a Context,
+    file: &File,
+    current_file: &Path,
+) -> Result<SchemaFile, ConfigError> {
     let file_descriptor = FileDescriptor {
         syntax: Syntax::Proto3,
-        import_paths: collect_required_imports(&context, &file)
+        import_paths: collect_required_imports(&context, &file, current_file)?
             .into_iter()
-            .map(|s| Path::new(&s.replace(".", "/")).with_extension("proto"))
             .collect(),
         ..Default::default()
     };
 
-    let mut builder = SchemaFileBuilder { context, file_descriptor };
-    extract::extract_from_file(&mut builder, file);
+    let mut builder = SchemaFileBuilder { context, file_descriptor, error: None };
+    extract::extract_from_file(&mut builder, file)?;
+    if let Some(error) = builder.error {
+        return Err(error);
+    }
 
-    SchemaFile(builder.file_descriptor)
+    let mut schema_file = SchemaFile(builder.file_descriptor);
+    if schema_file.has_options() {
+        schema_file.import_paths.push(PathBuf::from(version::OPTIONS_PROTO_PATH));
+    }
+    Ok(schema_file)
 }
 
 pub struct SchemaFile(FileDescriptor);
@@ -312,11 +548,51 @@ impl Default for SchemaFile {
 impl SchemaFile {
     pub fn merge(&mut self, other: &mut SchemaFile) {
         self.0.import_paths.append(&mut other.0.import_paths);
+        self.0.import_paths.sort();
         self.0.import_paths.dedup();
         self.0.enums.append(&mut other.0.enums);
         self.0.messages.append(&mut other.0.messages);
     }
 
+    pub fn has_schema_versions(&self) -> bool {
+        self.0.messages.iter().any(|m| m.schema_version.is_some())
+            || self.0.enums.iter().any(|e| e.schema_version.is_some())
+    }
+
+    /// Returns whether any type has an option of `protobuf_gen/options.proto`.
+    pub fn has_options(&self) -> bool {
+        fn has_options(message: &Message) -> bool {
+            message.schema_version.is_some()
+                || message.unique_by.is_some()
+                || message.fields.iter().any(|field| field.sensitive)
+                || message
+                    .oneofs
+                    .iter()
+                    .flat_map(|oneof| &oneof.fields)
+                    .any(|field| field.sensitive)
+                || message.messages.iter().any(has_options)
+        }
+
+        self.0.messages.iter().any(has_options)
+            || self.0.enums.iter().any(|e| e.schema_version.is_some())
+    }
+
+    /// Returns whether any field is `optional`, which `protoc` before 3.15 only accepts with
+    /// `--experimental_allow_proto3_optional`.
+    pub fn has_optional_fields(&self) -> bool {
+        fn has_optional_fields(message: &Message) -> bool {
+            message.fields.iter().any(|field| field.frequency == Frequency::Optional)
+                || message.messages.iter().any(has_optional_fields)
+        }
+
+        self.0.messages.iter().any(has_optional_fields)
+    }
+
+    /// Returns whether any type is converted to `google.rpc.Status`, which is not in the schemas.
+    pub fn has_status(&self) -> bool {
+        self.0.messages.iter().any(|message| message.status)
+    }
+
     pub fn release(self) -> FileDescriptor {
         self.0
     }
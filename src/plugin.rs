@@ -0,0 +1,148 @@
+//! Helpers for plugins which exchange messages with their host over stdin/stdout.
+//!
+//! Each message is prefixed with the length of its encoding as a varint, which is the framing of
+//! `prost::Message::encode_length_delimited`. A plugin usually receives a request enum and sends
+//! back a response enum, each derived as a `oneof` message. With `dispatch`, the derive generates
+//! a handler trait with a method for each variant of the request, called by `dispatch`:
+//!
+//! ```ignore
+//! #[derive(ProtobufGen)]
+//! #[protobuf_gen(proxy_mod = "crate::proxy", dispatch = "Response")]
+//! pub enum Request {
+//!     Register(City),
+//!     Count,
+//! }
+//!
+//! // generated
+//! pub trait RequestHandler {
+//!     fn register(&mut self, request: City) -> Response;
+//!     fn count(&mut self) -> Response;
+//! }
+//!
+//! impl Plugin for Directory {
+//!     type Request = Request;
+//!     type Response = Response;
+//!
+//!     fn handle(&mut self, request: Request) -> Response {
+//!         request.dispatch(self)
+//!     }
+//! }
+//! ```
+
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::marker::PhantomData;
+use std::process::{Child, ChildStdin, ChildStdout, Command, ExitStatus, Stdio};
+
+use crate::{Error, ProtobufGen};
+
+pub fn write_message<W: Write, T: ProtobufGen<Error = Error>>(
+    w: &mut W,
+    message: T,
+) -> Result<(), Error> {
+    let mut buffer = Vec::new();
+    message.to_protobuf(&mut buffer)?;
+
+    let mut length = Vec::with_capacity(prost::length_delimiter_len(buffer.len()));
+    prost::encoding::encode_varint(buffer.len() as u64, &mut length);
+    w.write_all(&length)?;
+    w.write_all(&buffer)?;
+    Ok(())
+}
+
+/// Returns `None` if `r` is closed before a new message starts.
+pub fn read_message<R: Read, T: ProtobufGen<Error = Error>>(r: &mut R) -> Result<Option<T>, Error> {
+    let length = match read_length(r)? {
+        Some(length) => length,
+        None => return Ok(None),
+    };
+
+    // the length is untrusted, so the buffer only grows with what is actually read
+    let mut buffer = Vec::new();
+    r.by_ref().take(length).read_to_end(&mut buffer)?;
+    if buffer.len() as u64 != length {
+        return Err(io::Error::new(ErrorKind::UnexpectedEof, "truncated message").into());
+    }
+    T::from_protobuf(&mut &buffer[..]).map(Some)
+}
+
+fn read_length<R: Read>(r: &mut R) -> io::Result<Option<u64>> {
+    let mut length = 0u64;
+    for i in 0..10 {
+        let mut byte = [0];
+        if r.read(&mut byte)? == 0 {
+            return if i == 0 {
+                Ok(None)
+            } else {
+                Err(io::Error::new(ErrorKind::UnexpectedEof, "truncated length delimiter"))
+            };
+        }
+
+        length |= u64::from(byte[0] & 0x7f) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(length));
+        }
+    }
+    Err(io::Error::new(ErrorKind::InvalidData, "invalid length delimiter"))
+}
+
+pub trait Plugin {
+    type Request: ProtobufGen<Error = Error>;
+    type Response: ProtobufGen<Error = Error>;
+
+    fn handle(&mut self, request: Self::Request) -> Self::Response;
+}
+
+/// Answers every request read from `r` until it is closed.
+pub fn serve<P: Plugin, R: Read, W: Write>(
+    plugin: &mut P,
+    r: &mut R,
+    w: &mut W,
+) -> Result<(), Error> {
+    while let Some(request) = read_message(r)? {
+        write_message(w, plugin.handle(request))?;
+        w.flush()?;
+    }
+    Ok(())
+}
+
+pub fn serve_stdio<P: Plugin>(plugin: &mut P) -> Result<(), Error> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    serve(plugin, &mut stdin.lock(), &mut stdout.lock())
+}
+
+/// A plugin running as a subprocess, called by its host.
+pub struct PluginProcess<Req, Resp> {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+    _marker: PhantomData<fn(Req) -> Resp>,
+}
+
+impl<Req, Resp> PluginProcess<Req, Resp>
+where
+    Req: ProtobufGen<Error = Error>,
+    Resp: ProtobufGen<Error = Error>,
+{
+    pub fn spawn(command: &mut Command) -> io::Result<Self> {
+        let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let stdin = BufWriter::new(child.stdin.take().unwrap());
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Ok(Self { child, stdin, stdout, _marker: PhantomData })
+    }
+
+    pub fn call(&mut self, request: Req) -> Result<Resp, Error> {
+        write_message(&mut self.stdin, request)?;
+        self.stdin.flush()?;
+        read_message(&mut self.stdout)?.ok_or_else(|| {
+            io::Error::new(ErrorKind::UnexpectedEof, "plugin exited without a response").into()
+        })
+    }
+
+    /// Closes the stdin of the plugin and waits for it to exit.
+    pub fn finish(self) -> io::Result<ExitStatus> {
+        let Self { mut child, stdin, .. } = self;
+        drop(stdin.into_inner().map_err(|e| e.into_error())?);
+        child.wait()
+    }
+}
@@ -6,8 +6,21 @@ use crate::types::{
 
 pub struct SchemaPrinter<'a>(pub &'a FileDescriptor);
 
+pub struct MessagePrinter<'a>(pub &'a Message);
+
+pub struct EnumPrinter<'a>(pub &'a Enumerator);
+
 fn print_enum(e: &Enumerator, indent: usize, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     writeln!(f, "{:indent$}enum {} {{", "", e.name, indent = indent)?;
+    if let Some(version) = e.schema_version {
+        writeln!(
+            f,
+            "{:indent$}  option (protobuf_gen.enum_schema_version) = {};",
+            "",
+            version,
+            indent = indent
+        )?;
+    }
     for (name, number) in &e.fields {
         writeln!(f, "{:indent$}  {} = {};", "", name, number, indent = indent)?;
     }
@@ -19,6 +32,7 @@ fn print_field(field: &Field, indent: usize, f: &mut fmt::Formatter<'_>) -> fmt:
     fn frequency_to_string(freq: &Frequency) -> &str {
         match freq {
             Frequency::Repeated => "repeated ",
+            Frequency::Optional => "optional ",
             _ => "",
         }
     }
@@ -51,12 +65,13 @@ fn print_field(field: &Field, indent: usize, f: &mut fmt::Formatter<'_>) -> fmt:
 
     writeln!(
         f,
-        "{:indent$}{}{} {} = {};",
+        "{:indent$}{}{} {} = {}{};",
         "",
         frequency_to_string(&field.frequency),
         type_to_string(&field.typ),
         field.name,
         field.number,
+        if field.sensitive { " [(protobuf_gen.sensitive) = true]" } else { "" },
         indent = indent
     )
 }
@@ -71,6 +86,24 @@ fn print_oneof(one_of: &OneOf, indent: usize, f: &mut fmt::Formatter<'_>) -> fmt
 
 fn print_message(message: &Message, indent: usize, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     writeln!(f, "{:indent$}message {} {{", "", message.name, indent = indent)?;
+    if let Some(version) = message.schema_version {
+        writeln!(
+            f,
+            "{:indent$}  option (protobuf_gen.schema_version) = {};",
+            "",
+            version,
+            indent = indent
+        )?;
+    }
+    if let Some(ref field) = message.unique_by {
+        writeln!(
+            f,
+            "{:indent$}  option (protobuf_gen.unique_by) = \"{}\";",
+            "",
+            field,
+            indent = indent
+        )?;
+    }
 
     for e in &message.enums {
         print_enum(e, indent + 2, f)?;
@@ -122,3 +155,15 @@ impl<'a> fmt::Display for SchemaPrinter<'a> {
         Ok(())
     }
 }
+
+impl<'a> fmt::Display for MessagePrinter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        print_message(self.0, 0, f)
+    }
+}
+
+impl<'a> fmt::Display for EnumPrinter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        print_enum(self.0, 0, f)
+    }
+}
@@ -0,0 +1,106 @@
+//! Checks whether every field of a value has its default value in protobuf, e.g. to skip
+//! encoding empty submessages or to treat unset config values as inherited.
+//!
+//! Scalars are default if they are zero, strings, bytes, repeated and map fields if they are
+//! empty, and `Option` fields if they are `None`. `#[derive(ProtobufGen)]` implements
+//! `ProtoDefault` for types with `#[protobuf_gen(proto_default)]`:
+//!
+//! - a `struct` is default if all of its fields in the schema are, recursively,
+//! - an `enum` of unit variants is default if it is its first variant, which is numbered 0,
+//! - any other `enum` is never default, since one field of its `oneof` is always set.
+//!
+//! A `#[protobuf_gen(substitute = "..")]` field is converted before the check, so its type has to
+//! be `Clone`. A `#[protobuf_gen(encrypt = "..")]` field is never default, since its ciphertext
+//! has to be encoded to be decrypted.
+//!
+//! The types of the other fields have to implement `ProtoDefault` as well, so a derived type
+//! with fields of e.g. `IndexMap` or of a type replaced by `Config::replace_type` implements it
+//! by hand.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryInto;
+
+pub trait ProtoDefault {
+    fn is_proto_default(&self) -> bool;
+}
+
+macro_rules! impl_proto_default_zero {
+    ($($t:ty),*) => {
+        $(
+            impl ProtoDefault for $t {
+                fn is_proto_default(&self) -> bool {
+                    *self == 0 as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_proto_default_zero!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+impl ProtoDefault for bool {
+    fn is_proto_default(&self) -> bool {
+        !*self
+    }
+}
+
+impl ProtoDefault for String {
+    fn is_proto_default(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl<T> ProtoDefault for Vec<T> {
+    fn is_proto_default(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl<T, const N: usize> ProtoDefault for [T; N] {
+    fn is_proto_default(&self) -> bool {
+        N == 0
+    }
+}
+
+impl<T> ProtoDefault for HashSet<T> {
+    fn is_proto_default(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl<K, V> ProtoDefault for HashMap<K, V> {
+    fn is_proto_default(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl<K, V> ProtoDefault for BTreeMap<K, V> {
+    fn is_proto_default(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+/// Only `None` is default, since a set message or `optional` field is encoded even if its value
+/// is default.
+impl<T> ProtoDefault for Option<T> {
+    fn is_proto_default(&self) -> bool {
+        self.is_none()
+    }
+}
+
+impl<T: ProtoDefault> ProtoDefault for Box<T> {
+    fn is_proto_default(&self) -> bool {
+        (**self).is_proto_default()
+    }
+}
+
+/// Checks a substituted field by its value converted into the type of `field` of the proxy,
+/// which is only named by the accessor.
+#[doc(hidden)]
+pub fn substitute<T, P, U>(value: T, _field: fn(P) -> U) -> bool
+where
+    T: TryInto<U>,
+    U: ProtoDefault,
+{
+    value.try_into().is_ok_and(|value: U| value.is_proto_default())
+}
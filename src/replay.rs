@@ -0,0 +1,297 @@
+//! Replays corpora encoded by earlier releases against the current schemas and conversions.
+//!
+//! A corpus is a sequence of length-delimited messages as written by [`record`] or the `corpus`
+//! subcommand. Keeping the corpus recorded by every release as a test fixture answers whether the
+//! current release can still read data written by an old one:
+//!
+//! ```ignore
+//! let descriptors = Descriptors::load("protos/yellow_book.bin")?;
+//! let report = Replay::new(&descriptors)
+//!     .run::<Person>("yellow_book.Person", &fs::read("tests/fixtures/person-0.4.0.bin")?)?;
+//! assert!(report.is_compatible(), "{}", report);
+//! ```
+use std::fmt;
+use std::io::Write;
+
+use prost::encoding::{decode_key, decode_varint, encode_varint, WireType};
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::FieldDescriptorProto;
+
+use crate::descriptor::{DescriptorError, Descriptors};
+use crate::ProtobufGen;
+
+/// The release of this crate, e.g. to name the corpora recorded with it.
+pub const RELEASE: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IncompatibilityKind {
+    /// The field number is not defined by the current schema, so the value is dropped.
+    UnknownField(WireType),
+    /// The field is encoded with a wire type that the current type of the field cannot have.
+    WireTypeMismatch {
+        expected: WireType,
+        found: WireType,
+    },
+    /// The value is not defined by the current enum.
+    UnknownEnumValue(i64),
+    InvalidUtf8,
+    /// The bytes are not a valid encoding, e.g. a truncated length-delimited field.
+    Malformed(String),
+    /// The generated conversion fails to decode the whole message.
+    Rejected(String),
+}
+
+/// An incompatibility found in the `frame`-th message of a corpus, at the field `path`, e.g.
+/// `Person.job.designer.name`. Fields unknown to the current schema are written as `#<number>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incompatibility {
+    pub frame: usize,
+    pub path: String,
+    pub kind: IncompatibilityKind,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "frame {}: {}: ", self.frame, self.path)?;
+        match &self.kind {
+            IncompatibilityKind::UnknownField(wire_type) => {
+                write!(f, "unknown field of wire type {:?}", wire_type)
+            }
+            IncompatibilityKind::WireTypeMismatch { expected, found } => {
+                write!(f, "expected wire type {:?}, found {:?}", expected, found)
+            }
+            IncompatibilityKind::UnknownEnumValue(value) => {
+                write!(f, "unknown enum value {}", value)
+            }
+            IncompatibilityKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            IncompatibilityKind::Malformed(e) => write!(f, "malformed: {}", e),
+            IncompatibilityKind::Rejected(e) => write!(f, "rejected: {}", e),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub frames: usize,
+    pub incompatibilities: Vec<Incompatibility>,
+}
+
+impl Report {
+    pub fn is_compatible(&self) -> bool {
+        self.incompatibilities.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} incompatibilities in {} frames", self.incompatibilities.len(), self.frames)?;
+        for incompatibility in &self.incompatibilities {
+            write!(f, "\n  {}", incompatibility)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Replay<'a> {
+    descriptors: &'a Descriptors,
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(descriptors: &'a Descriptors) -> Self {
+        Self { descriptors }
+    }
+
+    /// Scans every message of `corpus` field by field against `message` of the current
+    /// descriptors, then decodes it as `T` with the generated conversion.
+    pub fn run<T>(&self, message: &str, corpus: &[u8]) -> Result<Report, DescriptorError>
+    where
+        T: ProtobufGen,
+        T::Error: fmt::Display,
+    {
+        let name = message.rsplit('.').next().unwrap_or(message);
+
+        let mut report = Report::default();
+        for (frame, bytes) in frames(corpus)?.into_iter().enumerate() {
+            let mut scan = Scan { frame, incompatibilities: &mut report.incompatibilities };
+            scan.message(self.descriptors, message, name, bytes)?;
+            if let Err(e) = T::from_protobuf(&mut &bytes[..]) {
+                report.incompatibilities.push(Incompatibility {
+                    frame,
+                    path: name.to_string(),
+                    kind: IncompatibilityKind::Rejected(e.to_string()),
+                });
+            }
+            report.frames += 1;
+        }
+        Ok(report)
+    }
+}
+
+/// Writes `values` to `w` as a corpus that later releases can replay.
+pub fn record<T, I, W>(values: I, w: &mut W) -> Result<(), T::Error>
+where
+    T: ProtobufGen,
+    T::Error: From<std::io::Error>,
+    I: IntoIterator<Item = T>,
+    W: Write,
+{
+    for value in values {
+        let mut message = Vec::new();
+        value.to_protobuf(&mut message)?;
+        let mut length = Vec::new();
+        encode_varint(message.len() as u64, &mut length);
+        w.write_all(&length)?;
+        w.write_all(&message)?;
+    }
+    Ok(())
+}
+
+/// Splits a corpus into its length-delimited messages.
+pub fn frames(mut corpus: &[u8]) -> Result<Vec<&[u8]>, DescriptorError> {
+    let mut frames = Vec::new();
+    while !corpus.is_empty() {
+        let len = decode_varint(&mut corpus)? as usize;
+        if len > corpus.len() {
+            return Err(DescriptorError::TruncatedCorpus);
+        }
+        let (frame, rest) = corpus.split_at(len);
+        frames.push(frame);
+        corpus = rest;
+    }
+    Ok(frames)
+}
+
+struct Scan<'r> {
+    frame: usize,
+    incompatibilities: &'r mut Vec<Incompatibility>,
+}
+
+impl<'r> Scan<'r> {
+    fn report(&mut self, path: String, kind: IncompatibilityKind) {
+        self.incompatibilities.push(Incompatibility { frame: self.frame, path, kind });
+    }
+
+    fn message(
+        &mut self,
+        descriptors: &Descriptors,
+        message: &str,
+        path: &str,
+        mut bytes: &[u8],
+    ) -> Result<(), DescriptorError> {
+        let message = descriptors.message(message)?;
+        while !bytes.is_empty() {
+            let (tag, wire_type) = match decode_key(&mut bytes) {
+                Ok(key) => key,
+                Err(e) => {
+                    self.report(path.to_string(), IncompatibilityKind::Malformed(e.to_string()));
+                    return Ok(());
+                }
+            };
+            let value = match take_value(wire_type, &mut bytes) {
+                Ok(value) => value,
+                Err(e) => {
+                    let path = format!("{}.#{}", path, tag);
+                    self.report(path, IncompatibilityKind::Malformed(e));
+                    return Ok(());
+                }
+            };
+
+            match message.field.iter().find(|field| field.number() as u32 == tag) {
+                Some(field) => {
+                    let path = format!("{}.{}", path, field.name());
+                    self.field(descriptors, field, path, wire_type, value)?;
+                }
+                None => self.report(
+                    format!("{}.#{}", path, tag),
+                    IncompatibilityKind::UnknownField(wire_type),
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    fn field(
+        &mut self,
+        descriptors: &Descriptors,
+        field: &FieldDescriptorProto,
+        path: String,
+        wire_type: WireType,
+        mut value: &[u8],
+    ) -> Result<(), DescriptorError> {
+        let expected = wire_type_of(field.r#type());
+        let packed = field.label() == Label::Repeated
+            && wire_type == WireType::LengthDelimited
+            && expected != WireType::LengthDelimited;
+        if wire_type != expected && !packed {
+            self.report(path, IncompatibilityKind::WireTypeMismatch { expected, found: wire_type });
+            return Ok(());
+        }
+
+        match field.r#type() {
+            Type::Message => self.message(descriptors, field.type_name(), &path, value)?,
+            Type::String if std::str::from_utf8(value).is_err() => {
+                self.report(path, IncompatibilityKind::InvalidUtf8)
+            }
+            Type::Enum => {
+                let values = &descriptors.enumeration(field.type_name())?.value;
+                while !value.is_empty() {
+                    match decode_varint(&mut value) {
+                        Ok(n) if values.iter().any(|v| v.number() as i64 == n as i64) => {}
+                        Ok(n) => self
+                            .report(path.clone(), IncompatibilityKind::UnknownEnumValue(n as i64)),
+                        Err(e) => {
+                            self.report(path, IncompatibilityKind::Malformed(e.to_string()));
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn wire_type_of(ty: Type) -> WireType {
+    match ty {
+        Type::Int32
+        | Type::Int64
+        | Type::Uint32
+        | Type::Uint64
+        | Type::Sint32
+        | Type::Sint64
+        | Type::Bool
+        | Type::Enum => WireType::Varint,
+        Type::Fixed32 | Type::Sfixed32 | Type::Float => WireType::ThirtyTwoBit,
+        Type::Fixed64 | Type::Sfixed64 | Type::Double => WireType::SixtyFourBit,
+        Type::String | Type::Bytes | Type::Message => WireType::LengthDelimited,
+        Type::Group => WireType::StartGroup,
+    }
+}
+
+// Returns the bytes of the value following a key: the varint itself, the fixed-size bytes, or
+// the payload of a length-delimited field.
+pub(crate) fn take_value<'b>(
+    wire_type: WireType,
+    bytes: &mut &'b [u8],
+) -> Result<&'b [u8], String> {
+    let len = match wire_type {
+        WireType::Varint => {
+            let start = *bytes;
+            decode_varint(bytes).map_err(|e| e.to_string())?;
+            return Ok(&start[..start.len() - bytes.len()]);
+        }
+        WireType::ThirtyTwoBit => 4,
+        WireType::SixtyFourBit => 8,
+        WireType::LengthDelimited => decode_varint(bytes).map_err(|e| e.to_string())? as usize,
+        WireType::StartGroup | WireType::EndGroup => {
+            return Err("groups are not supported".to_string())
+        }
+    };
+    if len > bytes.len() {
+        return Err(format!("truncated value of {} bytes", len));
+    }
+    let (value, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(value)
+}
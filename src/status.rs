@@ -0,0 +1,113 @@
+//! Structured service errors as `google.rpc.Status`.
+//!
+//! `#[protobuf_gen(status = "<package>.<Name>")]` on an `enum` converted to a message maps it to
+//! `Status`, with the protobuf name of the `enum` given for the type URL of its details, which
+//! `Config::generate` checks against the package the `enum` is added to:
+//!
+//! ```ignore
+//! #[derive(Debug, ProtobufGen, thiserror::Error)]
+//! #[protobuf_gen(proxy_mod = "crate::proxy", status = "yellow_book.DirectoryError")]
+//! pub enum DirectoryError {
+//!     #[protobuf_gen(code = "NOT_FOUND")]
+//!     #[error("no entry for {0}")]
+//!     NotFound(String),
+//!     #[protobuf_gen(code = "RESOURCE_EXHAUSTED")]
+//!     #[error("directory is full")]
+//!     Full { capacity: u32 },
+//! }
+//! ```
+//!
+//! `From<DirectoryError> for Status` sets the `code` of the variant, any but `OK`, or `UNKNOWN` if
+//! none is given, the `Display` of the error as the `message`, and the error itself packed in an
+//! `Any` as the only `details`. `TryFrom<Status> for DirectoryError` unpacks the details again, so only
+//! statuses created from the error convert back.
+//!
+//! The generator writes `google/rpc/status.proto` next to the schemas if any type has `status`, so
+//! that clients in other languages decode the same `Status`.
+
+use std::fmt;
+
+use prost_types::Any;
+
+pub use crate::extract::status::Code;
+use crate::{Error, ProtobufGen};
+
+pub const STATUS_PROTO_PATH: &str = "google/rpc/status.proto";
+
+pub const STATUS_PROTO: &str = r#"syntax = "proto3";
+
+package google.rpc;
+
+import "google/protobuf/any.proto";
+
+option go_package = "google.golang.org/genproto/googleapis/rpc/status;status";
+option java_multiple_files = true;
+option java_outer_classname = "StatusProto";
+option java_package = "com.google.rpc";
+option objc_class_prefix = "RPC";
+
+message Status {
+  int32 code = 1;
+  string message = 2;
+  repeated google.protobuf.Any details = 3;
+}
+"#;
+
+pub const TYPE_URL_PREFIX: &str = "type.googleapis.com/";
+
+/// `google.rpc.Status`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Status {
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: String,
+    #[prost(message, repeated, tag = "3")]
+    pub details: Vec<Any>,
+}
+
+impl Status {
+    pub fn new<T: Into<String>>(code: Code, message: T) -> Self {
+        Self { code: code as i32, message: message.into(), details: Vec::new() }
+    }
+
+    /// Returns the code, unless it is not a canonical one.
+    pub fn canonical_code(&self) -> Option<Code> {
+        Code::from_i32(self.code)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.canonical_code() {
+            Some(code) => write!(f, "{}: {}", code.name(), self.message),
+            None => write!(f, "{}: {}", self.code, self.message),
+        }
+    }
+}
+
+impl std::error::Error for Status {}
+
+/// Creates the status of `error`, whose details are left empty if it fails to encode, since a
+/// service reporting an error should not fail to report it.
+#[doc(hidden)]
+pub fn pack<T: ProtobufGen>(code: Code, message: String, error: T, type_name: &str) -> Status {
+    let mut value = Vec::new();
+    let details = match error.to_protobuf(&mut value) {
+        Ok(()) => vec![Any { type_url: format!("{}{}", TYPE_URL_PREFIX, type_name), value }],
+        Err(_) => Vec::new(),
+    };
+    Status { code: code as i32, message, details }
+}
+
+/// Decodes the details of `status` packed from the error `type_name`.
+#[doc(hidden)]
+pub fn unpack<T: ProtobufGen<Error = Error>>(status: &Status, type_name: &str) -> Result<T, Error> {
+    let type_url = format!("{}{}", TYPE_URL_PREFIX, type_name);
+    let any = status
+        .details
+        .iter()
+        .find(|any| any.type_url == type_url)
+        .ok_or_else(|| Error::new_empty_object(type_url))?;
+    T::from_protobuf(&mut &any.value[..])
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use sha2::{Digest, Sha256};
+
+use crate::{Error, ProtobufGen};
+
+/// The SHA-256 digest of an encoded message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContentHash(pub [u8; 32]);
+
+impl ContentHash {
+    pub fn of(encoded: &[u8]) -> Self {
+        let mut hash = [0; 32];
+        hash.copy_from_slice(&Sha256::digest(encoded));
+        Self(hash)
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Stores messages keyed by the hash of their encoding, so equal messages are stored once.
+///
+/// The protobuf encoding of a message is used as its canonical encoding, and fields are encoded in
+/// field number order. It is not canonical for every type, and equal messages of such types may
+/// be stored more than once, with hashes which differ between processes:
+///
+/// - the entries of a `HashMap` field are encoded in iteration order, so use a `BTreeMap` instead.
+/// - a `#[protobuf_gen(encrypt = "..")]` field is encoded as its ciphertext, which depends on the
+///   keyring, e.g. on a random nonce.
+#[derive(Debug)]
+pub struct ContentStore<T> {
+    contents: HashMap<ContentHash, Vec<u8>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for ContentStore<T> {
+    fn default() -> Self {
+        Self { contents: HashMap::new(), _marker: PhantomData }
+    }
+}
+
+impl<T: ProtobufGen<Error = Error>> ContentStore<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.contents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
+    }
+
+    pub fn contains(&self, hash: &ContentHash) -> bool {
+        self.contents.contains_key(hash)
+    }
+
+    pub fn put(&mut self, value: T) -> Result<ContentHash, Error> {
+        let mut encoded = Vec::new();
+        value.to_protobuf(&mut encoded)?;
+        Ok(self.insert(encoded))
+    }
+
+    pub fn put_batch<I: IntoIterator<Item = T>>(
+        &mut self,
+        values: I,
+    ) -> Result<Vec<ContentHash>, Error> {
+        values.into_iter().map(|value| self.put(value)).collect()
+    }
+
+    pub fn get(&self, hash: &ContentHash) -> Result<Option<T>, Error> {
+        self.get_encoded(hash).map(|mut encoded| T::from_protobuf(&mut encoded)).transpose()
+    }
+
+    pub fn get_batch<'a, I: IntoIterator<Item = &'a ContentHash>>(
+        &self,
+        hashes: I,
+    ) -> Result<Vec<Option<T>>, Error> {
+        hashes.into_iter().map(|hash| self.get(hash)).collect()
+    }
+
+    /// Stores an encoded message, failing if it does not decode as `T`.
+    pub fn put_encoded(&mut self, encoded: Vec<u8>) -> Result<ContentHash, Error> {
+        T::from_protobuf(&mut &encoded[..])?;
+        Ok(self.insert(encoded))
+    }
+
+    /// Stores an encoded message which was stored with `hash` before, e.g. one loaded from a
+    /// snapshot, failing if the encoding does not match `hash` or does not decode as `T`.
+    pub fn load(&mut self, hash: ContentHash, encoded: Vec<u8>) -> Result<(), Error> {
+        if ContentHash::of(&encoded) != hash {
+            return Err(Error::new_integrity_error(hash));
+        }
+        T::from_protobuf(&mut &encoded[..])?;
+        self.contents.entry(hash).or_insert(encoded);
+        Ok(())
+    }
+
+    pub fn load_batch<I: IntoIterator<Item = (ContentHash, Vec<u8>)>>(
+        &mut self,
+        contents: I,
+    ) -> Result<(), Error> {
+        contents.into_iter().try_for_each(|(hash, encoded)| self.load(hash, encoded))
+    }
+
+    fn insert(&mut self, encoded: Vec<u8>) -> ContentHash {
+        let hash = ContentHash::of(&encoded);
+        self.contents.entry(hash).or_insert(encoded);
+        hash
+    }
+
+    pub fn get_encoded(&self, hash: &ContentHash) -> Option<&[u8]> {
+        self.contents.get(hash).map(Vec::as_slice)
+    }
+
+    pub fn iter_encoded(&self) -> impl Iterator<Item = (&ContentHash, &[u8])> {
+        self.contents.iter().map(|(hash, encoded)| (hash, encoded.as_slice()))
+    }
+}
@@ -65,6 +65,8 @@ pub struct Field {
     pub packed: Option<bool>,
     pub boxed: bool,
     pub deprecated: bool,
+    /// Randomized by `anonymize::sample_encoded`, from `#[protobuf_gen(sensitive)]`.
+    pub sensitive: bool,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -82,6 +84,13 @@ pub struct Message {
     pub path: PathBuf,
     pub import: PathBuf,
     pub index: MessageIndex,
+    pub schema_version: Option<u32>,
+    /// The field by which `anonymize::sample_encoded` skips duplicates, from
+    /// `#[protobuf_gen(unique_by = "..")]`.
+    pub unique_by: Option<String>,
+    /// Whether the message is packed in the details of `google.rpc.Status`, from
+    /// `#[protobuf_gen(status = "..")]`.
+    pub status: bool,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -96,6 +105,7 @@ pub struct Enumerator {
     pub path: PathBuf,
     pub import: PathBuf,
     pub index: EnumIndex,
+    pub schema_version: Option<u32>,
 }
 
 #[derive(Debug, Clone, Default)]
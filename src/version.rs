@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use crate::parse::SchemaFile;
+use crate::print::{EnumPrinter, MessagePrinter};
+use crate::ConfigError;
+
+pub const OPTIONS_PROTO_PATH: &str = "protobuf_gen/options.proto";
+
+pub const OPTIONS_PROTO: &str = r#"syntax = "proto3";
+
+package protobuf_gen;
+
+import "google/protobuf/descriptor.proto";
+
+extend google.protobuf.MessageOptions {
+  uint32 schema_version = 50000;
+  string unique_by = 50001;
+}
+
+extend google.protobuf.FieldOptions {
+  bool sensitive = 50000;
+}
+
+extend google.protobuf.EnumOptions {
+  uint32 enum_schema_version = 50000;
+}
+"#;
+
+/// The last known `schema_version` and schema hash of every versioned type.
+///
+/// The lock is stored as one `<package>.<Name> <version> <hash>` line per type.
+#[derive(Debug, Default, PartialEq)]
+pub struct SchemaLock {
+    entries: BTreeMap<String, (u32, u64)>,
+}
+
+impl SchemaLock {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+
+        let mut entries = BTreeMap::new();
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let invalid =
+                || io::Error::new(ErrorKind::InvalidData, format!("invalid line: {}", line));
+            let mut tokens = line.split_whitespace();
+            let name = tokens.next().ok_or_else(invalid)?;
+            let version = tokens.next().and_then(|v| v.parse().ok()).ok_or_else(invalid)?;
+            let hash =
+                tokens.next().and_then(|h| u64::from_str_radix(h, 16).ok()).ok_or_else(invalid)?;
+            entries.insert(name.to_string(), (version, hash));
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let content: String = self
+            .entries
+            .iter()
+            .map(|(name, (version, hash))| format!("{} {} {:016x}\n", name, version, hash))
+            .collect();
+        fs::write(path, content)
+    }
+
+    /// Records the versioned types of `schema_file`, failing if a schema has changed while its
+    /// version has not been bumped.
+    pub fn update(&mut self, schema_file: &SchemaFile) -> Result<(), ConfigError> {
+        for message in &schema_file.messages {
+            if let Some(version) = message.schema_version {
+                let mut message = message.clone();
+                message.schema_version = None;
+                let hash = schema_hash(&MessagePrinter(&message).to_string());
+                self.record(format!("{}.{}", schema_file.package, message.name), version, hash)?;
+            }
+        }
+
+        for e in &schema_file.enums {
+            if let Some(version) = e.schema_version {
+                let mut e = e.clone();
+                e.schema_version = None;
+                let hash = schema_hash(&EnumPrinter(&e).to_string());
+                self.record(format!("{}.{}", schema_file.package, e.name), version, hash)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, name: String, version: u32, hash: u64) -> Result<(), ConfigError> {
+        if let Some(&(locked_version, locked_hash)) = self.entries.get(&name) {
+            if version < locked_version {
+                return Err(ConfigError::SchemaVersionDecreased(name, locked_version, version));
+            }
+            if hash != locked_hash && version == locked_version {
+                return Err(ConfigError::SchemaVersionNotBumped(name, locked_version));
+            }
+        }
+        self.entries.insert(name, (version, hash));
+        Ok(())
+    }
+}
+
+// 64-bit FNV-1a, which unlike `DefaultHasher` is stable across Rust releases.
+fn schema_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
@@ -0,0 +1,90 @@
+//! A fixed binary layout of scalar-only messages, for peers which cannot run a protobuf parser.
+//!
+//! `#[protobuf_gen(wire_struct)]` generates `to_wire_struct` and `from_wire_struct` for a
+//! `struct` whose fields in the schema are all of these types:
+//!
+//! | type                 | size | encoding                          |
+//! |----------------------|------|-----------------------------------|
+//! | `bool`               | 1    | `0` or `1`                        |
+//! | `u8`, `i8`           | 1    |                                   |
+//! | `u16`, `i16`         | 2    | big-endian, two's complement      |
+//! | `u32`, `i32`, `f32`  | 4    | big-endian, IEEE 754 for `f32`    |
+//! | `u64`, `i64`, `f64`  | 8    | big-endian, IEEE 754 for `f64`    |
+//!
+//! The layout starts with a byte of `VERSION`, followed by the fields in the order of their
+//! field numbers without any padding. `WIRE_STRUCT_LEN` of the type is its total size.
+//!
+//! `VERSION` only changes with the encoding above, not with the fields of a type, so peers must
+//! check `WIRE_STRUCT_LEN` as well, as `from_wire_struct` does. A change of the fields which keeps
+//! the size, e.g. swapping two `u32` fields, is not detected by either, so such types should have
+//! `#[protobuf_gen(schema_version = N)]`, whose `SCHEMA_VERSION` a schema lock forces to be bumped
+//! when the fields change.
+
+use crate::Error;
+
+/// The version of the encoding of wire structs, written as their first byte.
+pub const VERSION: u8 = 1;
+
+/// A field type of a wire struct.
+pub trait WireScalar: Sized {
+    const LEN: usize;
+
+    fn put(self, bytes: &mut Vec<u8>);
+
+    /// Reads the value from the first `LEN` bytes of `bytes`, which must be long enough.
+    fn take(bytes: &mut &[u8]) -> Self;
+}
+
+macro_rules! impl_wire_scalar {
+    ($($t:ty),*) => {
+        $(
+            impl WireScalar for $t {
+                const LEN: usize = std::mem::size_of::<$t>();
+
+                fn put(self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&self.to_be_bytes());
+                }
+
+                fn take(bytes: &mut &[u8]) -> Self {
+                    let (head, tail) = bytes.split_at(Self::LEN);
+                    let mut buffer = [0; std::mem::size_of::<$t>()];
+                    buffer.copy_from_slice(head);
+                    *bytes = tail;
+                    Self::from_be_bytes(buffer)
+                }
+            }
+        )*
+    };
+}
+
+impl_wire_scalar!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+impl WireScalar for bool {
+    const LEN: usize = 1;
+
+    fn put(self, bytes: &mut Vec<u8>) {
+        bytes.push(self as u8);
+    }
+
+    fn take(bytes: &mut &[u8]) -> Self {
+        u8::take(bytes) != 0
+    }
+}
+
+/// Checks the version and the length of `bytes`, and returns the fields following the version.
+#[doc(hidden)]
+pub fn payload<T: ToString>(bytes: &[u8], len: usize, ident: T) -> Result<&[u8], Error> {
+    if bytes.len() != len {
+        return Err(Error::new_wire_struct_error(
+            ident,
+            format!("expected {} bytes, but got {}", len, bytes.len()),
+        ));
+    }
+    match bytes.split_first() {
+        Some((&VERSION, payload)) => Ok(payload),
+        Some((version, _)) => {
+            Err(Error::new_wire_struct_error(ident, format!("unknown version {}", version)))
+        }
+        None => Err(Error::new_wire_struct_error(ident, "no version")),
+    }
+}
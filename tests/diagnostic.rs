@@ -0,0 +1,117 @@
+use protobuf_gen::diagnostic::{check_item, Diagnostic};
+
+fn error_of(item: &str) -> String {
+    let item: syn::Item = syn::parse_str(item).unwrap();
+    check_item(&item).unwrap_err().to_string()
+}
+
+#[test]
+fn diagnostic_prefixes_are_stable() {
+    assert_eq!(Diagnostic::UnknownAttribute.prefix(), "protobuf_gen::unknown_attribute");
+    assert_eq!(Diagnostic::InvalidAttribute.prefix(), "protobuf_gen::invalid_attribute");
+    assert_eq!(Diagnostic::InvalidProxyMod.prefix(), "protobuf_gen::invalid_proxy_mod");
+    assert_eq!(Diagnostic::UnsupportedItem.prefix(), "protobuf_gen::unsupported_item");
+    assert_eq!(Diagnostic::UnsupportedVariant.prefix(), "protobuf_gen::unsupported_variant");
+}
+
+#[test]
+fn check_item_reports_diagnostics() {
+    let cases = [
+        (
+            "#[protobuf_gen(proxy)] pub struct Shop { pub name: String }",
+            "protobuf_gen::unknown_attribute: ",
+        ),
+        (
+            "pub struct Shop { #[protobuf_gen(exposed)] pub name: String }",
+            "protobuf_gen::unknown_attribute: ",
+        ),
+        (
+            "#[protobuf_gen(proxy_mod = 1)] pub struct Shop { pub name: String }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "#[protobuf_gen = 1] pub struct Shop { pub name: String }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "#[protobuf_gen(proxy_mod = \"crate::proxy-mod\")] pub struct Shop { pub name: String }",
+            "protobuf_gen::invalid_proxy_mod: ",
+        ),
+        ("pub struct Shop(pub String);", "protobuf_gen::unsupported_item: "),
+        ("pub struct Shop { name: String }", "protobuf_gen::unsupported_item: "),
+        ("pub type Shop = String;", "protobuf_gen::unsupported_item: "),
+        ("pub enum Owner { Pair(u32, u32) }", "protobuf_gen::unsupported_variant: "),
+        ("pub enum Event { Open, Close() }", "protobuf_gen::unsupported_variant: "),
+        (
+            "pub enum Event { Open(#[protobuf_gen(inherent = true)] Session) }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "pub enum Failure { #[protobuf_gen(code = \"NOT_FOUND\")] Missing(String) }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "#[protobuf_gen(status = \"shop.Failure\")] pub enum Failure { #[protobuf_gen(code = \"OK\")] Missing(String) }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "pub struct Account { #[protobuf_gen(encrypt = \"pii\")] pub email: Option<String> }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "pub struct Account { #[protobuf_gen(encrypt = \"pii\")] pub id: u32 }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "pub struct Account { #[protobuf_gen(encrypt = \"pii\")] pub keys: Vec<String> }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "pub enum Contact { Email(#[protobuf_gen(encrypt = \"pii\")] String) }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "#[protobuf_gen(file = \"/etc/people.proto\")] pub struct Person { pub name: String }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "#[protobuf_gen(file = \"../people.proto\")] pub struct Person { pub name: String }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "#[protobuf_gen(file = \"people/../../people.proto\")] pub enum Job { Programmer, Designer }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "#[protobuf_gen(file = \"people.rs\")] pub struct Person { pub name: String }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+        (
+            "#[protobuf_gen(file = \"people\")] pub struct Person { pub name: String }",
+            "protobuf_gen::invalid_attribute: ",
+        ),
+    ];
+    for (item, prefix) in &cases {
+        let error = error_of(item);
+        assert!(error.starts_with(prefix), "{}: {}", item, error);
+    }
+
+    let item: syn::Item = syn::parse_str(
+        "#[protobuf_gen(proxy_mod = \"crate::proxy\")] pub struct Shop { pub name: String }",
+    )
+    .unwrap();
+    assert!(check_item(&item).is_ok());
+
+    let item: syn::Item = syn::parse_str(
+        "pub struct Account { #[protobuf_gen(encrypt = \"pii\")] pub email: String, \
+         #[protobuf_gen(encrypt = \"pii\")] pub key: Vec<u8> }",
+    )
+    .unwrap();
+    assert!(check_item(&item).is_ok());
+
+    let item: syn::Item = syn::parse_str(
+        "#[protobuf_gen(file = \"people/person.proto\")] pub struct Person { pub name: String }",
+    )
+    .unwrap();
+    assert!(check_item(&item).is_ok());
+}
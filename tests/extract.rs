@@ -0,0 +1,244 @@
+use std::path::PathBuf;
+
+use protobuf_gen_extract::index::{ProtoType, TypeIndex};
+use protobuf_gen_extract::{
+    Attribute, Conversions, Extract, OneOfVariant, OneOfVariantKind, OptionalEnum, Target,
+};
+use syn::{Field, ItemEnum, Type};
+
+fn type_to_string(ty: &Type) -> String {
+    match ty {
+        Type::Path(type_path) => type_path.path.segments.last().unwrap().ident.to_string(),
+        _ => unreachable!(),
+    }
+}
+
+#[derive(Default)]
+struct Recorder {
+    events: Vec<String>,
+}
+
+fn target_to_string(target: &Target) -> String {
+    let mut path = target.item.to_string();
+    for ident in target.variant.iter().chain(target.field.iter()) {
+        path += &format!("::{}", ident);
+    }
+    path
+}
+
+impl Extract for Recorder {
+    fn extract_one_of_variant(&mut self, item_enum: &ItemEnum, variant: &OneOfVariant) {
+        let kind = match variant.kind {
+            OneOfVariantKind::Unit => "unit".to_string(),
+            OneOfVariantKind::Message(fields_named) => {
+                format!("{} fields", fields_named.named.len())
+            }
+            OneOfVariantKind::Type(ty) => type_to_string(ty),
+        };
+        self.events.push(format!(
+            "variant {}::{} = {} ({})",
+            item_enum.ident, variant.variant.ident, variant.number, kind
+        ));
+    }
+
+    fn extract_optional_enum(&mut self, item_enum: &ItemEnum, optional_enum: &OptionalEnum) {
+        self.events.push(format!(
+            "optional enum {} {} {}({})",
+            item_enum.ident,
+            optional_enum.none.ident,
+            optional_enum.some.ident,
+            type_to_string(optional_enum.ty)
+        ));
+    }
+
+    fn extract_attributes(&mut self, target: &Target, attributes: &[Attribute]) {
+        let keys: Vec<_> = attributes.iter().map(|attribute| attribute.key.as_str()).collect();
+        self.events.push(format!("attributes {} {:?}", target_to_string(target), keys));
+    }
+
+    fn extract_doc(&mut self, target: &Target, doc: &str) {
+        self.events.push(format!("doc {} {:?}", target_to_string(target), doc));
+    }
+
+    fn extract_field(&mut self, target: &Target, _: &Field) {
+        self.events.push(format!("field {}", target_to_string(target)));
+    }
+
+    fn extract_map_field(&mut self, target: &Target, _: &Field, key: &Type, value: &Type) {
+        self.events.push(format!(
+            "map {} {} {}",
+            target_to_string(target),
+            type_to_string(key),
+            type_to_string(value)
+        ));
+    }
+
+    fn extract_optional_field(&mut self, target: &Target, _: &Field, inner: &Type) {
+        self.events.push(format!(
+            "optional {} {}",
+            target_to_string(target),
+            type_to_string(inner)
+        ));
+    }
+}
+
+#[test]
+fn extract_callbacks() {
+    let file = syn::parse_file(
+        r#"
+        /// A shop.
+        /// Selling things.
+        #[derive(ProtobufGen)]
+        #[protobuf_gen(proxy_mod = "crate::proxy")]
+        pub struct Shop {
+            #[protobuf_gen(sensitive)]
+            pub owner: Option<String>,
+            pub stock: HashMap<String, u32>,
+            hidden: BTreeMap<String, u32>,
+        }
+
+        #[derive(ProtobufGen)]
+        pub enum Event {
+            /// Opened.
+            Open,
+            Sell { items: Vec<u32> },
+            Close(Shop),
+        }
+
+        #[derive(ProtobufGen)]
+        #[protobuf_gen(optional)]
+        pub enum Owner {
+            Some(String),
+            None,
+        }
+
+        pub struct Ignored {
+            pub note: Option<String>,
+        }
+
+        lazy_static! {
+            static ref IGNORED: u32 = 0;
+        }
+
+        protobuf_gen::conversions!(Point => proxy_mod = crate::proxy, inherent, fields(
+            x: i32,
+            #[protobuf_gen(sensitive)]
+            label: Option<String>,
+        ));
+        "#,
+    )
+    .unwrap();
+
+    let mut recorder = Recorder::default();
+    protobuf_gen_extract::extract_from_file(&mut recorder, &file).unwrap();
+    assert_eq!(
+        recorder.events,
+        vec![
+            r#"attributes Shop ["proxy_mod"]"#,
+            r#"doc Shop "A shop.\nSelling things.""#,
+            r#"attributes Shop::owner ["sensitive"]"#,
+            "field Shop::owner",
+            "optional Shop::owner String",
+            "field Shop::stock",
+            "map Shop::stock String u32",
+            "variant Event::Open = 1 (unit)",
+            "variant Event::Sell = 2 (1 fields)",
+            "variant Event::Close = 3 (Shop)",
+            r#"doc Event::Open "Opened.""#,
+            "field Event::Sell::items",
+            "optional enum Owner None Some(String)",
+            r#"attributes Owner ["optional"]"#,
+            r#"attributes Point ["proxy_mod", "inherent"]"#,
+            "field Point::x",
+            r#"attributes Point::label ["sensitive"]"#,
+            "field Point::label",
+            "optional Point::label String",
+        ]
+    );
+}
+
+#[test]
+fn extract_rejects_empty_tuple_variant() {
+    let item = syn::parse_str(
+        r#"
+        #[derive(ProtobufGen)]
+        pub enum Event {
+            Open,
+            Close(),
+        }
+        "#,
+    )
+    .unwrap();
+
+    let error = protobuf_gen_extract::extract_item(&mut Recorder::default(), &item).unwrap_err();
+    assert!(error.to_string().starts_with("protobuf_gen::unsupported_variant: "), "{}", error);
+}
+
+#[test]
+fn extract_rejects_unsupported_items() {
+    for source in &[
+        "#[derive(ProtobufGen)] pub struct Shop(pub String);",
+        "#[derive(ProtobufGen)] pub struct Shop { name: String }",
+        "#[derive(ProtobufGen)] pub enum Owner { Pair(u32, u32) }",
+    ] {
+        let file = syn::parse_file(source).unwrap();
+        let error =
+            protobuf_gen_extract::extract_from_file(&mut Recorder::default(), &file).unwrap_err();
+        assert!(error.to_string().starts_with("protobuf_gen::unsupported_"), "{}", error);
+    }
+}
+
+#[test]
+fn conversions_match_protobuf_gen_only() {
+    let conversions_of = |source: &str| {
+        let file = syn::parse_file(source).unwrap();
+        let item = file.items.last().unwrap();
+        Conversions::from_item_in(item, &file.items).map(|c| c.ident.to_string())
+    };
+    let invocation = "(Point => proxy_mod = crate::proxy, fields(x: i32));";
+
+    assert_eq!(
+        conversions_of(&format!("protobuf_gen::conversions!{}", invocation)),
+        Some("Point".to_string())
+    );
+    assert_eq!(conversions_of(&format!("conversions!{}", invocation)), None);
+    assert_eq!(conversions_of(&format!("other::conversions!{}", invocation)), None);
+    assert_eq!(
+        conversions_of(&format!("use protobuf_gen::conversions; conversions!{}", invocation)),
+        Some("Point".to_string())
+    );
+    assert_eq!(
+        conversions_of(&format!(
+            "use protobuf_gen::{{ProtobufGen, conversions as convert}}; convert!{}",
+            invocation
+        )),
+        Some("Point".to_string())
+    );
+    assert_eq!(
+        conversions_of(&format!("use protobuf_gen::*; conversions!{}", invocation)),
+        Some("Point".to_string())
+    );
+    assert_eq!(
+        conversions_of(&format!("use other::conversions; conversions!{}", invocation)),
+        None
+    );
+}
+
+#[test]
+fn index_keeps_types_of_same_name_in_different_packages() {
+    let ty = |package: &str, inherent| ProtoType {
+        package: package.to_string(),
+        path: PathBuf::from(format!("{}.proto", package)),
+        inherent,
+    };
+    let mut index = TypeIndex::default();
+    index.insert("City", ty("yellow_book", false));
+    index.insert("City", ty("atlas", false));
+    index.insert("Street", ty("atlas", true));
+
+    let index = TypeIndex::parse(&index.to_string());
+    assert_eq!(index.find("City").count(), 2);
+    assert_eq!(index.get("City"), None);
+    assert_eq!(index.get("Street"), Some(&ty("atlas", true)));
+    assert_eq!(index.get("Street").unwrap().full_name("Street"), "atlas.Street");
+}
@@ -1,6 +1,9 @@
+use std::fs;
+use std::process::Command;
 use std::result;
 
-use protobuf_gen::{Config, ConfigError};
+use protobuf_gen::backend::{Backend, ProtobufBackend, Schema};
+use protobuf_gen::{settings, status, Config, ConfigError, Settings};
 
 #[test]
 fn unittest_yellow_book() -> result::Result<(), ConfigError> {
@@ -9,7 +12,426 @@ fn unittest_yellow_book() -> result::Result<(), ConfigError> {
     let mut config = Config::new("protos", Some("proxy"));
     config.add_source("lib_tests/src/person.rs", "yellow_book");
     config.add_source("lib_tests/src/city.rs", "yellow_book");
+    config.add_source("lib_tests/src/account.rs", "yellow_book");
+    config.add_source("lib_tests/src/directory.rs", "yellow_book");
+    config.add_source("lib_tests/src/sensor.rs", "yellow_book");
+    config.add_source("lib_tests/src/geometry.rs", "yellow_book");
+    config.ffi_header("protos/ffi/yellow_book.h");
 
     config.generate()?;
+
+    let header = fs::read_to_string("protos/ffi/yellow_book.h")?;
+    assert!(header.contains("typedef struct yellow_book_City yellow_book_City;"));
+    assert!(header
+        .contains("yellow_book_City *yellow_book_city_decode(const uint8_t *data, size_t len);"));
+    assert!(header.contains("void yellow_book_city_free(yellow_book_City *value);"));
+    assert!(!header.contains("person_decode"));
+    Ok(())
+}
+
+#[test]
+fn unittest_schema_version_lock() -> result::Result<(), ConfigError> {
+    let dir = std::env::temp_dir().join("protobuf_gen_schema_version_lock");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+
+    let source = dir.join("shop.rs");
+    let write_source = |version: u32, field: &str| {
+        fs::write(
+            &source,
+            format!(
+                "#[derive(ProtobufGen)]\n#[protobuf_gen(schema_version = {})]\npub struct Shop {{ pub {}: String }}\n",
+                version, field
+            ),
+        )
+    };
+
+    let mut config = Config::new(dir.join("protos"), None::<&str>);
+    config.add_source(&source, "shop");
+    config.check_schema_versions(dir.join("schema.lock"));
+
+    write_source(1, "name")?;
+    config.generate()?;
+    assert!(fs::read_to_string(dir.join("protos/shop.proto"))?
+        .contains("option (protobuf_gen.schema_version) = 1;"));
+
+    write_source(1, "title")?;
+    assert!(
+        matches!(config.generate(), Err(ConfigError::SchemaVersionNotBumped(name, 1)) if name == "shop.Shop")
+    );
+
+    write_source(2, "title")?;
+    config.generate()?;
+
+    write_source(1, "title")?;
+    assert!(matches!(
+        config.generate(),
+        Err(ConfigError::SchemaVersionDecreased(name, 2, 1)) if name == "shop.Shop"
+    ));
+
+    fs::write(
+        &source,
+        "#[derive(ProtobufGen)]\n#[protobuf_gen(schema_version = 4294967296)]\npub struct Shop { pub title: String }\n",
+    )?;
+    assert!(matches!(
+        config.generate(),
+        Err(ConfigError::InvalidSchemaVersion(name, 4294967296)) if name == "shop.Shop"
+    ));
+    Ok(())
+}
+
+#[test]
+fn unittest_proto_file_attribute() -> result::Result<(), ConfigError> {
+    let dir = std::env::temp_dir().join("protobuf_gen_proto_file_attribute");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+
+    fs::write(
+        dir.join("person.rs"),
+        "#[derive(ProtobufGen)]\n#[protobuf_gen(file = \"people.proto\")]\npub struct Person { pub name: String, pub home: Address }\n\
+         #[derive(ProtobufGen)]\npub struct Address { pub street: String }\n",
+    )?;
+    fs::write(
+        dir.join("team.rs"),
+        "#[derive(ProtobufGen)]\n#[protobuf_gen(file = \"people.proto\")]\npub struct Team { pub members: Vec<Person> }\n",
+    )?;
+    fs::write(
+        dir.join("shop.rs"),
+        "#[derive(ProtobufGen)]\npub struct Shop { pub owner: Person }\n",
+    )?;
+
+    let mut config = Config::new(dir.join("protos"), None::<&str>);
+    config.add_source(dir.join("person.rs"), "directory");
+    config.add_source(dir.join("team.rs"), "directory");
+    config.add_source(dir.join("shop.rs"), "shop");
+    config.descriptor_set(dir.join("descriptors.bin"));
+    config.generate()?;
+
+    let people = fs::read_to_string(dir.join("protos/people.proto"))?;
+    assert!(people.contains("package directory;"));
+    assert!(people.contains("import \"directory.proto\";"));
+    assert!(people.find("message Person {").unwrap() < people.find("message Team {").unwrap());
+
+    let directory = fs::read_to_string(dir.join("protos/directory.proto"))?;
+    assert!(directory.contains("message Address {"));
+    assert!(!directory.contains("import") && !directory.contains("message Person {"));
+
+    let shop = fs::read_to_string(dir.join("protos/shop.proto"))?;
+    assert!(shop.contains("import \"people.proto\";"));
+    assert!(shop.contains("directory.Person owner = 1;"));
+
+    fs::write(
+        dir.join("other.rs"),
+        "#[derive(ProtobufGen)]\n#[protobuf_gen(file = \"people.proto\")]\npub struct Other { pub id: u32 }\n",
+    )?;
+    config.add_source(dir.join("other.rs"), "other");
+    assert!(
+        matches!(config.generate(), Err(ConfigError::ConflictingPackages(path, ..)) if path == "people.proto")
+    );
+
+    // the generator rejects paths outside the target directory as the derive does
+    let mut config = Config::new(dir.join("protos"), None::<&str>);
+    fs::write(
+        dir.join("escape.rs"),
+        "#[derive(ProtobufGen)]\n#[protobuf_gen(file = \"../escape.proto\")]\npub struct Escape { pub id: u32 }\n",
+    )?;
+    config.add_source(dir.join("escape.rs"), "escape");
+    match config.generate() {
+        Err(ConfigError::ParseError(e)) => {
+            assert!(e.to_string().starts_with("protobuf_gen::invalid_attribute: "), "{}", e)
+        }
+        result => panic!("{:?}", result),
+    }
+    assert!(!dir.join("escape.proto").exists());
+    Ok(())
+}
+
+#[test]
+fn unittest_settings() -> result::Result<(), ConfigError> {
+    let dir = std::env::temp_dir().join("protobuf_gen_settings");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+
+    fs::write(
+        dir.join("shop.rs"),
+        "#[derive(ProtobufGen)]\npub struct Shop { pub id: i32, pub count: u64, pub owner: Option<Owner> }\n\
+         #[derive(ProtobufGen)]\npub struct Owner { pub name: String }\n",
+    )?;
+
+    let settings = Settings::parse(
+        "# comment\npackage_prefix = \"corp\"\ndefault_int_encoding = \"fixed\" # trailing comment\n",
+    )?;
+    let mut config = Config::new(dir.join("protos"), None::<&str>);
+    config.settings(settings);
+    config.add_source(dir.join("shop.rs"), "shop");
+    config.descriptor_set(dir.join("descriptors.bin"));
+    config.generate()?;
+
+    let shop = fs::read_to_string(dir.join("protos/corp/shop.proto"))?;
+    assert!(shop.contains("package corp.shop;"));
+    assert!(shop.contains("sfixed32 id = 1;"));
+    assert!(shop.contains("fixed64 count = 2;"));
+    assert!(shop.contains("  Owner owner = 3;"));
+
+    fs::write(
+        dir.join("person.rs"),
+        "#[derive(ProtobufGen)]\npub enum AreaCode { Seoul, Jinhae }\n\
+         #[derive(ProtobufGen)]\npub enum Job { Programmer { skill: String }, Designer(u32) }\n\
+         #[derive(ProtobufGen)]\npub struct Person { pub age: Option<u8>, pub area: AreaCode, pub job: Job }\n",
+    )?;
+
+    let mut config = Config::new(dir.join("protos"), None::<&str>);
+    config.settings(Settings::parse("naming = \"aip\"\noptional_style = \"proto3\"\n")?);
+    config.add_source(dir.join("person.rs"), "person");
+    config.descriptor_set(dir.join("descriptors.bin"));
+    config.generate()?;
+
+    let person = fs::read_to_string(dir.join("protos/person.proto"))?;
+    assert!(person.contains("  AREA_CODE_JINHAE = 1;"));
+    assert!(person.contains("    ProgrammerInner programmer = 1;"));
+    assert!(person.contains("    uint32 designer = 2;"));
+    assert!(person.contains("  optional uint32 age = 1;"));
+    // the derive names the values of `PROTO_NAMES` alike
+    let aip = Settings::parse("naming = \"aip\"")?;
+    assert_eq!(aip.enum_value_name("AreaCode", "Jinhae"), "AREA_CODE_JINHAE");
+
+    let settings = Settings::parse("package_prefix = \"corp#1\" # comment")?;
+    assert_eq!(settings.package_prefix.as_deref(), Some("corp#1"));
+    assert!(Settings::parse("package_prefix = \"corp\" 1").is_err());
+
+    assert!(matches!(
+        Settings::parse("naming = \"aip\"\nnaming = \"camel\""),
+        Err(settings::SettingsError::Parse { line: 2, .. })
+    ));
+    assert!(Settings::parse("unknown = \"1\"").is_err());
+    Ok(())
+}
+
+#[test]
+fn unittest_one_of_unnamed_variant() -> result::Result<(), ConfigError> {
+    let dir = std::env::temp_dir().join("protobuf_gen_one_of_unnamed_variant");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+
+    let source = dir.join("directory.rs");
+    fs::write(
+        &source,
+        "#[derive(ProtobufGen)]\npub struct City { pub name: String }\n\
+         #[derive(ProtobufGen)]\npub enum Request { Register(City), Count(u32), Lookup { name: String } }\n",
+    )?;
+
+    let mut config = Config::new(dir.join("protos"), None::<&str>);
+    config.add_source(&source, "directory");
+    config.generate()?;
+
+    // a variant with a single unnamed field is a field of its type, not a nested message
+    let directory = fs::read_to_string(dir.join("protos/directory.proto"))?;
+    assert!(directory.contains("    City Register = 1;"), "{}", directory);
+    assert!(directory.contains("    uint32 Count = 2;"), "{}", directory);
+    assert!(directory.contains("    LookupInner Lookup = 3;"), "{}", directory);
+    assert!(!directory.contains("RegisterInner"));
+    assert!(!directory.contains("CountInner"));
+    Ok(())
+}
+
+#[test]
+fn unittest_optional_enum() -> result::Result<(), ConfigError> {
+    let dir = std::env::temp_dir().join("protobuf_gen_optional_enum");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+
+    let source = dir.join("signup.rs");
+    let optional = "#[derive(ProtobufGen)]\n#[protobuf_gen(optional)]\npub enum Referrer { None, Account(u32) }\n";
+    fs::write(
+        &source,
+        format!(
+            "{}#[derive(ProtobufGen)]\npub struct Signup {{ pub referrer: Referrer }}\n",
+            optional
+        ),
+    )?;
+
+    // `optional` whatever the `optional_style` is
+    let mut config = Config::new(dir.join("protos"), None::<&str>);
+    config.settings(Settings::default());
+    config.add_source(&source, "signup");
+    config.descriptor_set(dir.join("signup.bin"));
+    config.generate()?;
+
+    let signup = fs::read_to_string(dir.join("protos/signup.proto"))?;
+    assert!(signup.contains("  optional uint32 referrer = 1;"));
+    assert!(!signup.contains("message Referrer"));
+
+    for ty in &["Vec<Referrer>", "HashMap<u32, Referrer>", "Option<Referrer>"] {
+        fs::write(
+            &source,
+            format!(
+                "{}#[derive(ProtobufGen)]\npub struct Signup {{ pub referrers: {} }}\n",
+                optional, ty
+            ),
+        )?;
+        match config.generate() {
+            Err(ConfigError::ParseError(e)) => {
+                assert!(e.to_string().starts_with("protobuf_gen::invalid_attribute: "), "{}", e)
+            }
+            result => panic!("{}: {:?}", ty, result),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn unittest_status_name() -> result::Result<(), ConfigError> {
+    let dir = std::env::temp_dir().join("protobuf_gen_status_name");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+
+    let source = dir.join("shop.rs");
+    let write_source = |status: &str| {
+        fs::write(
+            &source,
+            format!(
+                "#[derive(ProtobufGen)]\n#[protobuf_gen(status = \"{}\")]\npub enum ShopError {{ Closed {{}}, Missing(String) }}\n",
+                status
+            ),
+        )
+    };
+
+    let mut config = Config::new(dir.join("protos"), None::<&str>);
+    config.settings(Settings::default());
+    config.add_source(&source, "shop");
+
+    write_source("shop.ShopError")?;
+    config.generate()?;
+    assert!(dir.join("protos").join(status::STATUS_PROTO_PATH).exists());
+
+    write_source("other.ShopError")?;
+    match config.generate() {
+        Err(ConfigError::ParseError(e)) => {
+            assert!(e.to_string().starts_with("protobuf_gen::invalid_attribute: "), "{}", e)
+        }
+        result => panic!("{:?}", result),
+    }
+    Ok(())
+}
+
+#[test]
+fn unittest_corpus_cli() -> result::Result<(), ConfigError> {
+    let dir = std::env::temp_dir().join("protobuf_gen_corpus_cli");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+
+    let source = dir.join("shop.rs");
+    fs::write(
+        &source,
+        "#[derive(ProtobufGen)]\npub struct Shop { pub name: String, pub stock: Vec<u32>, pub owner: Owner }\n\
+         #[derive(ProtobufGen)]\npub enum Owner { Person { name: String }, Company { id: u64 } }\n",
+    )?;
+
+    let mut config = Config::new(dir.join("protos"), None::<&str>);
+    config.add_source(&source, "shop");
+    config.descriptor_set(dir.join("shop.bin"));
+    config.generate()?;
+
+    let corpus = |output: &str| {
+        let status = Command::new(env!("CARGO_BIN_EXE_protobuf-gen"))
+            .arg("corpus")
+            .arg("--descriptor-set")
+            .arg(dir.join("shop.bin"))
+            .args(["--message", "shop.Shop", "--count", "10", "--seed", "3", "--output"])
+            .arg(dir.join(output))
+            .status()
+            .unwrap();
+        assert!(status.success());
+        fs::read(dir.join(output)).unwrap()
+    };
+
+    let encoded = corpus("first.bin");
+    assert_eq!(encoded, corpus("second.bin"));
+
+    let mut buf = &encoded[..];
+    let mut count = 0;
+    while !buf.is_empty() {
+        let len = prost::encoding::decode_varint(&mut buf).unwrap() as usize;
+        buf = &buf[len..];
+        count += 1;
+    }
+    assert_eq!(count, 10);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_protobuf-gen"))
+        .arg("sizes")
+        .arg("--descriptor-set")
+        .arg(dir.join("shop.bin"))
+        .args(["--message", "shop.Shop", "--input"])
+        .arg(dir.join("first.bin"))
+        .output()?;
+    assert!(output.status.success());
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(report.starts_with(&format!("shop.Shop: 10 messages, {} bytes", encoded.len() - 10)));
+    assert!(report.lines().any(|line| line.starts_with("Shop.name ")));
+
+    for args in &[
+        &["--mesage", "shop.Shop"][..],
+        &["--message", "shop.Shop", "--message", "shop.Owner"],
+        &["--message", "shop.Shop", "--seed", "3"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_protobuf-gen"))
+            .arg("sizes")
+            .arg("--descriptor-set")
+            .arg(dir.join("shop.bin"))
+            .args(*args)
+            .args(["--input"])
+            .arg(dir.join("first.bin"))
+            .output()?;
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+    }
+    Ok(())
+}
+
+// Lists the messages of every schema, one `<package>.<Name>` per line.
+struct MessageList;
+
+impl Backend for MessageList {
+    fn generate(
+        &self,
+        config: &Config,
+        _: &Settings,
+        schemas: &[Schema],
+    ) -> result::Result<(), ConfigError> {
+        let mut lines = Vec::new();
+        for schema in schemas {
+            for message in &schema.file.messages {
+                lines.push(format!("{}.{}", schema.file.package, message.name));
+            }
+        }
+        fs::create_dir_all(&config.proto_target_dir)?;
+        fs::write(config.proto_target_dir.join("messages.txt"), lines.join("\n"))?;
+        Ok(())
+    }
+}
+
+#[test]
+fn unittest_backend() -> result::Result<(), ConfigError> {
+    let dir = std::env::temp_dir().join("protobuf_gen_backend");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+
+    let source = dir.join("shop.rs");
+    fs::write(
+        &source,
+        "#[derive(ProtobufGen)]\npub struct Shop { pub name: String, pub owner: Owner }\n\
+         #[derive(ProtobufGen)]\npub enum Owner { Person { name: String }, Company { id: u64 } }\n",
+    )?;
+
+    let mut config = Config::new(dir.join("out"), None::<&str>);
+    config.add_source(&source, "shop");
+    config.backend(MessageList);
+    config.generate()?;
+
+    assert_eq!(fs::read_to_string(dir.join("out/messages.txt"))?, "shop.Shop\nshop.Owner");
+    assert!(!dir.join("out/shop.proto").exists());
+
+    config.backend(ProtobufBackend);
+    config.generate()?;
+    assert!(fs::read_to_string(dir.join("out/shop.proto"))?.contains("message Shop {"));
     Ok(())
 }